@@ -0,0 +1,194 @@
+//! Temporal supersampling (motion blur) by frame accumulation.
+//!
+//! With motion blur enabled, the frontend renders `samples` sub-frames per
+//! output frame, spread across the shutter interval:
+//!
+//! ```text
+//! t = (frame + shutter_angle / 360 * i / samples) / fps    for i in 0..samples
+//! ```
+//!
+//! Each sub-frame is sent with `send_frame_rgba` as usual. The writer thread
+//! sums them into a float buffer and hands the average to the real sink once
+//! every `samples` sub-frames, so the output still has one frame per `frame`.
+//! Colour is decoded from sRGB and averaged in linear light, so bright
+//! highlights smear as they would on film instead of darkening.
+
+use serde::{Deserialize, Serialize};
+
+use crate::frame::{srgb_to_linear, FrameFormat};
+use crate::writer::FrameSink;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MotionBlur {
+    /// Sub-frames averaged into each output frame (1 disables blur).
+    pub samples: u32,
+    /// Fraction of the frame interval the shutter is open, in degrees (0–360).
+    /// Only the frontend's sub-frame timing depends on it.
+    pub shutter_angle: f32,
+}
+
+impl MotionBlur {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=256).contains(&self.samples) {
+            return Err(format!("Motion blur samples must be 1–256, got {}", self.samples));
+        }
+        if !(0.0..=360.0).contains(&self.shutter_angle) {
+            return Err(format!("Shutter angle must be 0–360°, got {}", self.shutter_angle));
+        }
+        Ok(())
+    }
+
+    /// Wrap `sink` so it receives one averaged frame per `samples` sub-frames,
+    /// in the same `input` layout the sub-frames arrive in.
+    pub fn wrap(self, mut sink: FrameSink, input: FrameFormat) -> FrameSink {
+        if self.samples <= 1 {
+            return sink;
+        }
+        let samples = self.samples;
+        let mut count = 0;
+        let mut sum: Vec<f32> = Vec::new();
+        let mut out: Vec<u8>  = Vec::new();
+        // sRGB decoding table for integer input; float input is already linear
+        let to_linear: Vec<f32> = match input {
+            FrameFormat::Rgba8   => (0..=255).map(|v| srgb_to_linear(v as f32 / 255.0)).collect(),
+            FrameFormat::Rgba16  => (0..=65535).map(|v| srgb_to_linear(v as f32 / 65535.0)).collect(),
+            FrameFormat::Rgba32f => Vec::new(),
+        };
+
+        Box::new(move |frame: &[u8]| {
+            let pixels = frame.len() / input.bytes_per_pixel();
+            if count == 0 {
+                sum.clear();
+                sum.resize(pixels * 4, 0.0);
+            }
+            match input {
+                FrameFormat::Rgba8 => accumulate(&mut sum, frame.chunks_exact(4).map(|px| {
+                    let c = |i: usize| to_linear[px[i] as usize];
+                    [c(0), c(1), c(2), px[3] as f32 / 255.0]
+                })),
+                FrameFormat::Rgba16 => accumulate(&mut sum, frame.chunks_exact(8).map(|px| {
+                    let v = |i: usize| u16::from_le_bytes([px[i * 2], px[i * 2 + 1]]);
+                    let c = |i: usize| to_linear[v(i) as usize];
+                    [c(0), c(1), c(2), v(3) as f32 / 65535.0]
+                })),
+                FrameFormat::Rgba32f => accumulate(&mut sum, frame.chunks_exact(16).map(|px| {
+                    let v = |i: usize| f32::from_le_bytes([px[i * 4], px[i * 4 + 1], px[i * 4 + 2], px[i * 4 + 3]]);
+                    [v(0), v(1), v(2), v(3)]
+                })),
+            }
+            count += 1;
+            if count < samples {
+                return Ok(());
+            }
+            count = 0;
+
+            out.resize(frame.len(), 0);
+            for (px, acc) in out.chunks_exact_mut(input.bytes_per_pixel()).zip(sum.chunks_exact(4)) {
+                let a = acc[3];
+                let colour = if a > 0.0 { [acc[0] / a, acc[1] / a, acc[2] / a] } else { [0.0; 3] };
+                input.write_linear_pixel([colour[0], colour[1], colour[2], a / samples as f32], px);
+            }
+            sink(&out)
+        })
+    }
+}
+
+/// Add linear pixels (alpha 0–1) to `sum`. Colour is premultiplied so
+/// transparent samples don't darken edges.
+fn accumulate(sum: &mut [f32], pixels: impl Iterator<Item = [f32; 4]>) {
+    for (acc, [r, g, b, a]) in sum.chunks_exact_mut(4).zip(pixels) {
+        acc[0] += r * a;
+        acc[1] += g * a;
+        acc[2] += b * a;
+        acc[3] += a;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    /// `blur.wrap` over a sink that records every frame it's given.
+    fn recorded(blur: MotionBlur) -> (FrameSink, Arc<Mutex<Vec<Vec<u8>>>>) {
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let log     = written.clone();
+        let sink    = blur.wrap(Box::new(move |frame: &[u8]| {
+            log.lock().unwrap().push(frame.to_vec());
+            Ok(())
+        }), FrameFormat::Rgba8);
+        (sink, written)
+    }
+
+    #[test]
+    fn averages_each_group_of_samples() {
+        let (mut sink, written) = recorded(MotionBlur { samples: 2, shutter_angle: 180.0 });
+        sink(&[0, 100, 200, 255]).unwrap();
+        assert!(written.lock().unwrap().is_empty());
+        sink(&[0, 100, 200, 255]).unwrap();
+        sink(&[10, 10, 10, 255]).unwrap();
+        sink(&[10, 10, 10, 255]).unwrap();
+        assert_eq!(*written.lock().unwrap(), vec![vec![0, 100, 200, 255], vec![10, 10, 10, 255]]);
+    }
+
+    #[test]
+    fn averages_in_linear_light() {
+        // Half white, half black is 50% linear light, i.e. sRGB 188 rather than 128
+        let (mut sink, written) = recorded(MotionBlur { samples: 2, shutter_angle: 180.0 });
+        sink(&[255, 255, 0, 255]).unwrap();
+        sink(&[0, 255, 0, 255]).unwrap();
+        assert_eq!(written.lock().unwrap()[0], vec![188, 255, 0, 255]);
+    }
+
+    #[test]
+    fn transparent_samples_dont_darken_colour() {
+        let (mut sink, written) = recorded(MotionBlur { samples: 2, shutter_angle: 360.0 });
+        sink(&[200, 100, 50, 255]).unwrap();
+        sink(&[0, 0, 0, 0]).unwrap();
+        assert_eq!(written.lock().unwrap()[0], vec![200, 100, 50, 128]);
+    }
+
+    #[test]
+    fn one_sample_passes_frames_through() {
+        let (mut sink, written) = recorded(MotionBlur { samples: 1, shutter_angle: 180.0 });
+        sink(&[1, 2, 3, 4]).unwrap();
+        assert_eq!(*written.lock().unwrap(), vec![vec![1, 2, 3, 4]]);
+    }
+
+    #[test]
+    fn validates_ranges() {
+        assert!(MotionBlur { samples: 0, shutter_angle: 180.0 }.validate().is_err());
+        assert!(MotionBlur { samples: 257, shutter_angle: 180.0 }.validate().is_err());
+        assert!(MotionBlur { samples: 8, shutter_angle: 361.0 }.validate().is_err());
+        assert!(MotionBlur { samples: 8, shutter_angle: 180.0 }.validate().is_ok());
+    }
+
+    #[test]
+    fn accumulates_deep_formats_in_their_own_layout() {
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let log     = written.clone();
+        let blur    = MotionBlur { samples: 2, shutter_angle: 180.0 };
+        let mut sink = blur.wrap(Box::new(move |frame: &[u8]| {
+            log.lock().unwrap().push(frame.to_vec());
+            Ok(())
+        }), FrameFormat::Rgba32f);
+        let bytes = |px: [f32; 4]| px.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>();
+        // HDR values above 1.0 survive; alpha is averaged
+        sink(&bytes([4.0, 0.0, 1.0, 1.0])).unwrap();
+        sink(&bytes([2.0, 1.0, 1.0, 0.0])).unwrap();
+        assert_eq!(written.lock().unwrap()[0], bytes([4.0, 0.0, 1.0, 0.5]));
+
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let log     = written.clone();
+        let mut sink = blur.wrap(Box::new(move |frame: &[u8]| {
+            log.lock().unwrap().push(frame.to_vec());
+            Ok(())
+        }), FrameFormat::Rgba16);
+        let px: Vec<u8> = [65535_u16, 0, 1000, 65535].iter().flat_map(|v| v.to_le_bytes()).collect();
+        sink(&px).unwrap();
+        sink(&px).unwrap();
+        assert_eq!(written.lock().unwrap()[0], px);
+    }
+}
@@ -0,0 +1,227 @@
+//! Atomic output writes.
+//!
+//! FFmpeg encodes (and tiled stills are written) into a hidden sibling of the
+//! chosen output, which is renamed over `output_path` only once writing has
+//! succeeded — a failed or aborted export never replaces an existing good
+//! file. Temp paths are recorded in the app data dir while in use, so ones left
+//! by a crash are removed on the next start. Each process keeps its own list
+//! and holds a lock on it while it runs, so a second instance never removes
+//! files the first is still writing.
+
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use fs2::FileExt;
+
+use tauri::{AppHandle, Manager};
+
+use crate::error::EncodeError;
+
+/// Per-process lists are named `pending-outputs-<pid>.json`.
+const PENDING_PREFIX: &str = "pending-outputs";
+
+/// Sibling temp file for `output`, e.g. `clip.mp4` → `.clip.mp4.1234-7.partial.mp4`.
+/// `owner` tells writers with separate id counters apart (an encode session id,
+/// `tile-3`). The original extension is kept last so FFmpeg still picks the
+/// right muxer.
+pub fn temp_path(output: &Path, owner: impl Display) -> PathBuf {
+    let name = output.file_name().map_or_else(|| "output".into(), |n| n.to_string_lossy());
+    let ext  = output.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    output.with_file_name(format!(".{name}.{}-{owner}.partial{ext}", std::process::id()))
+}
+
+/// Move a finished temp file onto `output` (replacing it if present).
+pub fn commit(temp: &Path, output: &Path) -> Result<(), EncodeError> {
+    std::fs::rename(temp, output).map_err(|e| {
+        let _ = std::fs::remove_file(temp);
+        EncodeError::Io { message: format!("Failed to move {} to {}: {e}", temp.display(), output.display()) }
+    })
+}
+
+/// Keep a truncated temp file: it becomes `output` if nothing is there yet,
+/// otherwise it's moved beside it (see `incomplete_path`) so an existing good
+/// file survives. Returns where it ended up.
+pub fn keep_truncated(temp: &Path, output: &Path) -> Result<PathBuf, EncodeError> {
+    let kept = if output.exists() { incomplete_path(output) } else { output.to_path_buf() };
+    commit(temp, &kept)?;
+    Ok(kept)
+}
+
+/// First free name of the form `clip.incomplete.mp4`, `clip.incomplete-2.mp4`, …
+pub fn incomplete_path(output: &Path) -> PathBuf {
+    let stem = output.file_stem().map_or_else(|| "output".into(), |n| n.to_string_lossy());
+    let ext  = output.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    (1..)
+        .map(|n| match n {
+            1 => output.with_file_name(format!("{stem}.incomplete{ext}")),
+            n => output.with_file_name(format!("{stem}.incomplete-{n}{ext}")),
+        })
+        .find(|path| !path.exists())
+        .expect("some numbered name is free")
+}
+
+/// Temp files currently being written, mirrored to disk.
+#[derive(Default)]
+pub struct PendingOutputs {
+    /// This process's list, locked for as long as the app runs. None if it
+    /// couldn't be created; tracking is then in memory only.
+    file: Option<File>,
+    paths: Mutex<BTreeSet<PathBuf>>,
+}
+
+impl PendingOutputs {
+    /// Delete temp files recorded by earlier runs that didn't shut down
+    /// cleanly, and start an empty list for this process.
+    pub fn recover(app: &AppHandle) -> Self {
+        let Ok(dir) = app.path().app_data_dir() else {
+            return Self::default();
+        };
+        remove_stale(&dir);
+        let path = dir.join(format!("{PENDING_PREFIX}-{}.json", std::process::id()));
+        let file = std::fs::create_dir_all(&dir)
+            .and_then(|()| OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&path))
+            .and_then(|file| file.try_lock_exclusive().map(|()| file));
+        match file {
+            Ok(file) => Self { file: Some(file), paths: Mutex::default() },
+            Err(e)   => {
+                log::warn!("Temp outputs won't survive a crash: can't lock {}: {e}", path.display());
+                Self::default()
+            }
+        }
+    }
+
+    pub fn add(&self, path: &Path) {
+        self.update(|paths| {
+            paths.insert(path.to_path_buf());
+        });
+    }
+
+    pub fn remove(&self, path: &Path) {
+        self.update(|paths| {
+            paths.remove(path);
+        });
+    }
+
+    /// Forget everything but `keep` — used once every session has been
+    /// aborted. `keep` holds temp files whose owners are still removing them,
+    /// so a crash before they finish leaves them for `recover`.
+    pub fn clear_except(&self, keep: &[PathBuf]) {
+        self.update(|paths| paths.retain(|path| keep.contains(path)));
+    }
+
+    fn update(&self, change: impl FnOnce(&mut BTreeSet<PathBuf>)) {
+        let mut paths = match self.paths.lock() {
+            Ok(p)       => p,
+            Err(poison) => poison.into_inner(),
+        };
+        change(&mut paths);
+        let Some(mut file) = self.file.as_ref() else { return };
+        // Best effort: losing the list only means a crash leaves a temp file
+        // behind. Written through the locked handle, as Windows locks are mandatory.
+        if let Ok(text) = serde_json::to_string_pretty(&*paths) {
+            let _ = file.set_len(0)
+                .and_then(|()| file.seek(SeekFrom::Start(0)))
+                .and_then(|_| file.write_all(text.as_bytes()));
+        }
+    }
+}
+
+/// Remove the temp files named by every pending list in `dir` that no running
+/// instance holds a lock on, then the lists themselves.
+fn remove_stale(dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !name.starts_with(PENDING_PREFIX) || !name.ends_with(".json") {
+            continue;
+        }
+        let list = entry.path();
+        let Ok(mut file) = OpenOptions::new().read(true).write(true).open(&list) else { continue };
+        if file.try_lock_exclusive().is_err() {
+            continue;   // another instance is still running
+        }
+        let mut text = String::new();
+        let stale: Vec<PathBuf> = file
+            .read_to_string(&mut text)
+            .ok()
+            .and_then(|_| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        for path in stale {
+            if std::fs::remove_file(&path).is_ok() {
+                log::info!("Removed stale temp output {}", path.display());
+            }
+        }
+        drop(file);
+        let _ = std::fs::remove_file(&list);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("shader-studio-atomic-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn temp_path_is_a_hidden_sibling_with_the_same_extension() {
+        let temp = temp_path(Path::new("/renders/clip.mp4"), "tile-3");
+        assert_eq!(temp.parent(), Some(Path::new("/renders")));
+        let name = temp.file_name().unwrap().to_string_lossy().into_owned();
+        assert_eq!(name, format!(".clip.mp4.{}-tile-3.partial.mp4", std::process::id()));
+        assert_ne!(temp_path(Path::new("clip.mp4"), 1), temp_path(Path::new("clip.mp4"), 2));
+    }
+
+    #[test]
+    fn commit_replaces_the_output() {
+        let dir    = scratch("commit");
+        let output = dir.join("clip.mp4");
+        let temp   = temp_path(&output, 1);
+        std::fs::write(&output, "old").unwrap();
+        std::fs::write(&temp, "new").unwrap();
+        commit(&temp, &output).unwrap();
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "new");
+        assert!(!temp.exists());
+        // A failed move still cleans up the temp file
+        std::fs::write(&temp, "new").unwrap();
+        assert!(commit(&temp, &dir.join("missing/clip.mp4")).is_err());
+        assert!(!temp.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn truncated_output_never_replaces_an_existing_file() {
+        let dir    = scratch("truncated");
+        let output = dir.join("clip.mp4");
+        let temp   = temp_path(&output, 1);
+
+        std::fs::write(&temp, "part").unwrap();
+        assert_eq!(keep_truncated(&temp, &output).unwrap(), output);
+
+        for expected in ["clip.incomplete.mp4", "clip.incomplete-2.mp4"] {
+            std::fs::write(&temp, "part").unwrap();
+            assert_eq!(keep_truncated(&temp, &output).unwrap(), dir.join(expected));
+        }
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "part");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn clearing_keeps_paths_still_owned() {
+        let pending = PendingOutputs::default();
+        for name in ["a", "b", "c"] {
+            pending.add(Path::new(name));
+        }
+        pending.remove(Path::new("c"));
+        pending.clear_except(&[PathBuf::from("b")]);
+        assert_eq!(*pending.paths.lock().unwrap(), BTreeSet::from([PathBuf::from("b")]));
+    }
+}
@@ -0,0 +1,173 @@
+//! Optional audio track muxed alongside the rendered video.
+//!
+//! The audio file is added as a second FFmpeg input. Offset and trim are
+//! applied on the input side (`-ss`/`-t`), fades and gain through an `-af`
+//! chain. The chain ends in `apad`, so audio shorter than the render is padded
+//! with silence, and `-shortest` then cuts the audio at the last frame — the
+//! video length always wins.
+
+use std::path::Path;
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioTrack {
+    /// Audio file to mux in.
+    pub path: String,
+    /// Seconds into the audio file where the export starts.
+    #[serde(default)]
+    pub offset: f64,
+    /// Length of audio to use, in seconds. Defaults to the video length.
+    pub duration: Option<f64>,
+    /// Fade-in length in seconds.
+    #[serde(default)]
+    pub fade_in: f64,
+    /// Fade-out length in seconds, ending at `duration` or else the video length.
+    #[serde(default)]
+    pub fade_out: f64,
+    /// Gain in dB applied after the fades.
+    #[serde(default)]
+    pub gain_db: f64,
+}
+
+impl AudioTrack {
+    /// Input args for the audio file; goes after the video input.
+    pub fn input_args(&self) -> Result<Vec<String>, String> {
+        if !Path::new(&self.path).is_file() {
+            return Err(format!("Audio file not found: {}", self.path));
+        }
+        if self.offset < 0.0 || self.fade_in < 0.0 || self.fade_out < 0.0 {
+            return Err("Audio offset and fade lengths must not be negative".into());
+        }
+        if self.duration.is_some_and(|d| d <= 0.0) {
+            return Err("Audio duration must be positive".into());
+        }
+        let mut args = Vec::new();
+        if self.offset > 0.0 {
+            args.extend(["-ss".into(), self.offset.to_string()]);
+        }
+        if let Some(duration) = self.duration {
+            args.extend(["-t".into(), duration.to_string()]);
+        }
+        args.extend(["-i".into(), self.path.clone()]);
+        Ok(args)
+    }
+
+    /// Output args: stream mapping, audio filters and codec for `output_path`'s
+    /// container. `video_secs` is the render length, if known; the fade-out
+    /// ends there unless `duration` is set.
+    pub fn output_args(&self, output_path: &str, video_secs: Option<f64>) -> Result<Vec<String>, String> {
+        let mut args: Vec<String> = ["-map", "0:v:0", "-map", "1:a:0"]
+            .iter().map(|s| s.to_string()).collect();
+
+        let mut filters = Vec::new();
+        if self.fade_in > 0.0 {
+            filters.push(format!("afade=t=in:st=0:d={}", self.fade_in));
+        }
+        if self.fade_out > 0.0 {
+            let duration = self.duration
+                .or(video_secs)
+                .ok_or("Audio fade-out needs a duration or the expected frame count")?;
+            let start    = (duration - self.fade_out).max(0.0);
+            filters.push(format!("afade=t=out:st={start}:d={}", self.fade_out));
+        }
+        if self.gain_db != 0.0 {
+            filters.push(format!("volume={}dB", self.gain_db));
+        }
+        // Pad with silence so -shortest stops at the video, never the audio
+        filters.push("apad".into());
+        args.extend(["-af".into(), filters.join(",")]);
+
+        let codec = audio_codec_for(output_path);
+        args.extend(["-c:a".into(), codec.into()]);
+        if codec == "aac" {
+            args.extend(["-b:a".into(), "192k".into()]);
+        }
+        args.push("-shortest".into());
+        Ok(args)
+    }
+}
+
+/// Audio codec suited to the output container: AAC for mp4, PCM for mov/mkv,
+/// Opus for webm.
+pub fn audio_codec_for(output_path: &str) -> &'static str {
+    let ext = Path::new(output_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match ext.as_str() {
+        "mov" | "mkv" => "pcm_s16le",
+        "webm"        => "libopus",
+        _             => "aac",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::arg_value;
+
+    fn track(path: &str) -> AudioTrack {
+        AudioTrack {
+            path: path.into(),
+            offset: 0.0,
+            duration: None,
+            fade_in: 0.0,
+            fade_out: 0.0,
+            gain_db: 0.0,
+        }
+    }
+
+    #[test]
+    fn trims_on_the_input_side() {
+        let file  = std::env::temp_dir().join(format!("shader-studio-audio-test-{}.wav", std::process::id()));
+        std::fs::write(&file, b"").unwrap();
+        let path  = file.to_string_lossy().into_owned();
+        let trim  = AudioTrack { offset: 1.5, duration: Some(10.0), ..track(&path) };
+        let args  = trim.input_args();
+        let bad   = AudioTrack { duration: Some(-5.0), ..track(&path) }.input_args();
+        let plain = track(&path).input_args();
+        std::fs::remove_file(&file).unwrap();
+
+        assert_eq!(args.unwrap(), ["-ss", "1.5", "-t", "10", "-i", path.as_str()]);
+        assert_eq!(plain.unwrap(), ["-i", path.as_str()]);
+        assert!(bad.is_err());
+        assert!(track("/no/such/audio.wav").input_args().is_err());
+    }
+
+    #[test]
+    fn chains_fades_and_gain_then_pads() {
+        let audio = AudioTrack {
+            duration: Some(10.0),
+            fade_in: 1.0,
+            fade_out: 2.0,
+            gain_db: -3.0,
+            ..track("a.wav")
+        };
+        let args = audio.output_args("out.mp4", Some(4.0)).unwrap();
+        assert_eq!(
+            arg_value(&args, "-af"),
+            Some("afade=t=in:st=0:d=1,afade=t=out:st=8:d=2,volume=-3dB,apad"),
+        );
+        assert_eq!(arg_value(&args, "-c:a"), Some("aac"));
+        assert_eq!(args.last().map(String::as_str), Some("-shortest"));
+    }
+
+    #[test]
+    fn fade_out_defaults_to_the_video_length() {
+        let audio = AudioTrack { fade_out: 1.0, ..track("a.wav") };
+        let args  = audio.output_args("out.mov", Some(5.0)).unwrap();
+        assert_eq!(arg_value(&args, "-af"), Some("afade=t=out:st=4:d=1,apad"));
+        assert!(audio.output_args("out.mov", None).is_err());
+    }
+
+    #[test]
+    fn picks_the_audio_codec_for_the_container() {
+        assert_eq!(audio_codec_for("a.mp4"), "aac");
+        assert_eq!(audio_codec_for("a.MOV"), "pcm_s16le");
+        assert_eq!(audio_codec_for("a.mkv"), "pcm_s16le");
+        assert_eq!(audio_codec_for("a.webm"), "libopus");
+    }
+}
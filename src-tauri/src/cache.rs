@@ -0,0 +1,438 @@
+//! On-disk cache of rendered frames, for render-once/encode-many.
+//!
+//! An encode started with `EncodeOptions::cache` also stores every frame it
+//! hands to FFmpeg — after motion-blur averaging, before FFmpeg's filters —
+//! deflate-compressed under the app cache dir. `reencode_from_cache` can then
+//! feed the same frames to any other encoder settings without rendering again.
+//!
+//! Entries are keyed by graph hash, resolution, fps and time range. A render
+//! is recorded into a `.partial` directory that replaces the entry only once
+//! the encode succeeds. The recorder holds a lock file inside it, so partial
+//! entries left by a crash (no live owner) are removed by `list` and `evict`.
+//! A re-encode reading a complete entry holds a shared lock on the same file
+//! (see `CacheReader`), so the entry isn't removed underneath it.
+//! Once the total size passes the limit (`Settings::frame_cache_limit`), the
+//! least recently used entries are evicted.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::error::EncodeError;
+use crate::frame::FrameFormat;
+use crate::ordering::FrameMap;
+use crate::writer::FrameSink;
+use crate::SessionId;
+
+const CACHE_DIR: &str = "frames";
+const MANIFEST_FILE: &str = "manifest.json";
+const LOCK_FILE: &str = "lock";
+const PARTIAL_EXT: &str = "partial";
+
+/// Used when no limit has been set.
+pub const DEFAULT_LIMIT: u64 = 20 * 1024 * 1024 * 1024;
+
+/// Identifies one render.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheKey {
+    /// Hash of the graph as rendered, computed by the frontend.
+    pub graph_hash: String,
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    /// Time range in seconds.
+    pub start: f64,
+    pub end: f64,
+}
+
+impl CacheKey {
+    /// Directory name — FNV-1a of the key, which stays stable across builds
+    /// unlike `std`'s hasher.
+    fn dir_name(&self) -> String {
+        let text = format!(
+            "{}|{}x{}|{}|{}-{}",
+            self.graph_hash, self.width, self.height, self.fps, self.start, self.end,
+        );
+        let hash = text.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |h, b| {
+            (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+        format!("{hash:016x}")
+    }
+}
+
+/// A cached render, as stored in its manifest and listed by `list_frame_cache`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheEntry {
+    pub key: CacheKey,
+    pub input_format: FrameFormat,
+    pub frames: u64,
+    /// Compressed size on disk.
+    pub bytes: u64,
+    /// False while recording, or if the render never finished.
+    pub complete: bool,
+    /// Unix seconds of the last write or re-encode.
+    pub last_used: u64,
+}
+
+/// Payload of the `cache-reencode` event, sent when a re-encode ends.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReencodeFinished {
+    pub session_id: SessionId,
+    pub frame_map: Option<FrameMap>,
+    pub error: Option<EncodeError>,
+}
+
+pub fn root(app: &AppHandle) -> Result<PathBuf, EncodeError> {
+    let dir = app.path().app_cache_dir().map_err(|e| EncodeError::Io { message: e.to_string() })?;
+    Ok(dir.join(CACHE_DIR))
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+fn frame_path(dir: &Path, index: u64) -> PathBuf {
+    dir.join(format!("{index:08}.deflate"))
+}
+
+fn read_manifest(dir: &Path) -> Option<CacheEntry> {
+    let text = std::fs::read_to_string(dir.join(MANIFEST_FILE)).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+fn write_manifest(dir: &Path, entry: &CacheEntry) -> Result<(), EncodeError> {
+    let text = serde_json::to_string_pretty(entry).map_err(|e| e.to_string())?;
+    std::fs::write(dir.join(MANIFEST_FILE), text).map_err(|e| EncodeError::Io {
+        message: format!("Failed to write cache manifest in {}: {e}", dir.display()),
+    })
+}
+
+/// Stores the frames of one encode session. Dropping it without `commit`
+/// removes what was recorded.
+pub struct CacheRecorder {
+    root: PathBuf,
+    /// Where frames are recorded until `commit` moves them into place.
+    dir: PathBuf,
+    limit: u64,
+    entry: Arc<Mutex<CacheEntry>>,
+    /// Set by the writer thread if a frame couldn't be stored; the entry is
+    /// then discarded instead of committed.
+    failed: Arc<AtomicBool>,
+    /// Held while recording so other processes can tell the entry is live.
+    lock: Option<File>,
+}
+
+impl CacheRecorder {
+    /// Start recording a new entry for `key`. Any earlier entry is kept until
+    /// `commit` replaces it. `owner` keeps concurrent recordings apart.
+    pub fn create(
+        root: &Path,
+        key: CacheKey,
+        input_format: FrameFormat,
+        limit: u64,
+        owner: SessionId,
+    ) -> Result<Self, EncodeError> {
+        let dir = root.join(format!("{}.{}-{owner}.{PARTIAL_EXT}", key.dir_name(), std::process::id()));
+        let io  = |e: std::io::Error| EncodeError::Io { message: format!("Failed to create {}: {e}", dir.display()) };
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).map_err(io)?;
+        let lock = File::create(dir.join(LOCK_FILE)).and_then(|f| f.try_lock_exclusive().map(|()| f));
+        // Built before checking the lock so dropping it cleans up on failure
+        let mut recorder = Self {
+            root: root.to_path_buf(),
+            dir: dir.clone(),
+            limit,
+            entry: Arc::new(Mutex::new(CacheEntry {
+                key, input_format, frames: 0, bytes: 0, complete: false, last_used: now(),
+            })),
+            failed: Arc::default(),
+            lock: None,
+        };
+        recorder.lock = Some(lock.map_err(io)?);
+        // Written only once locked: a manifest with a free lock means abandoned
+        write_manifest(&dir, &*recorder.entry.lock()?)?;
+        Ok(recorder)
+    }
+
+    /// Wrap `sink` so each frame is also stored in the cache. A cache write
+    /// failure is logged and stops caching, but doesn't fail the encode.
+    pub fn tee(&self, mut sink: FrameSink) -> FrameSink {
+        let dir    = self.dir.clone();
+        let entry  = self.entry.clone();
+        let failed = self.failed.clone();
+        Box::new(move |frame: &[u8]| {
+            if !failed.load(Ordering::Relaxed) {
+                let mut entry = entry.lock()?;
+                match write_frame(&frame_path(&dir, entry.frames), frame) {
+                    Ok(bytes) => {
+                        entry.frames += 1;
+                        entry.bytes  += bytes;
+                    }
+                    Err(e) => {
+                        log::warn!("Frame cache disabled for this render: {e}");
+                        failed.store(true, Ordering::Relaxed);
+                    }
+                }
+            }
+            sink(frame)
+        })
+    }
+
+    /// Mark the entry complete after a successful encode, move it over any
+    /// earlier entry for the key, then evict old entries down to the limit.
+    /// Returns why the render wasn't cached, if it wasn't.
+    pub fn commit(mut self) -> Option<String> {
+        if self.failed.load(Ordering::Relaxed) {
+            return Some("Frame cache write failed; the render wasn't cached".into());
+        }
+        let lock   = self.lock.take();
+        let result = self.entry.lock().map_err(EncodeError::from).and_then(|mut entry| {
+            // Committing would only evict everything else, then this entry
+            if entry.bytes > self.limit {
+                return Ok(Some(format!(
+                    "Render needs {} MiB, more than the {} MiB frame cache limit; it wasn't cached",
+                    entry.bytes / (1024 * 1024), self.limit / (1024 * 1024),
+                )));
+            }
+            entry.complete  = true;
+            entry.last_used = now();
+            write_manifest(&self.dir, &entry)?;
+            // Complete entries need no lock, and Windows won't move an open file
+            drop(lock);
+            let target = self.root.join(entry.key.dir_name());
+            if is_being_read(&target) {
+                return Ok(Some("The previous render for these settings is being re-encoded; this one wasn't cached".into()));
+            }
+            let _ = std::fs::remove_dir_all(&target);
+            std::fs::rename(&self.dir, &target).map_err(|e| EncodeError::Io {
+                message: format!("Failed to move {} to {}: {e}", self.dir.display(), target.display()),
+            })?;
+            Ok(None)
+        });
+        match result {
+            Ok(None) => {
+                evict(&self.root, self.limit);
+                None
+            }
+            Ok(warning) => warning,
+            Err(e)      => {
+                log::warn!("{e}");
+                Some(e.to_string())
+            }
+        }
+    }
+
+    /// Remove the recorded frames — the encode was aborted or failed. Same as
+    /// dropping the recorder.
+    pub fn discard(self) {
+        drop(self);
+    }
+}
+
+impl Drop for CacheRecorder {
+    fn drop(&mut self) {
+        // Unlock first; Windows won't remove an open file. After a commit the
+        // directory has already been moved away.
+        self.lock = None;
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Write one frame compressed; returns the bytes written.
+fn write_frame(path: &Path, frame: &[u8]) -> Result<u64, EncodeError> {
+    let err  = |e: std::io::Error| EncodeError::Io { message: format!("Failed to write {}: {e}", path.display()) };
+    let file = File::create(path).map_err(err)?;
+    let mut encoder = DeflateEncoder::new(BufWriter::new(file), Compression::fast());
+    encoder.write_all(frame).map_err(err)?;
+    let mut out = encoder.finish().map_err(err)?;
+    out.flush().map_err(err)?;
+    Ok(out.get_ref().metadata().map_err(err)?.len())
+}
+
+/// Every entry in the cache, most recently used first, including ones still
+/// being recorded. Incomplete entries no running encode owns are removed.
+pub fn list(root: &Path) -> Vec<CacheEntry> {
+    let Ok(dirs) = std::fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<CacheEntry> = dirs
+        .filter_map(|d| d.ok())
+        .filter_map(|d| {
+            let dir   = d.path();
+            let entry = read_manifest(&dir);
+            if entry.as_ref().is_some_and(|e| e.complete) {
+                return entry;
+            }
+            if entry.is_some() && is_abandoned(&dir) {
+                log::info!("Removing abandoned cache entry {}", dir.display());
+                let _ = std::fs::remove_dir_all(&dir);
+                return None;
+            }
+            entry
+        })
+        .collect();
+    entries.sort_by_key(|e| std::cmp::Reverse(e.last_used));
+    entries
+}
+
+/// Whether an incomplete entry has no recorder holding its lock.
+fn is_abandoned(dir: &Path) -> bool {
+    match OpenOptions::new().write(true).open(dir.join(LOCK_FILE)) {
+        Ok(lock) => lock.try_lock_exclusive().is_ok(),
+        // Recorded before entries had lock files
+        Err(_)   => true,
+    }
+}
+
+/// The complete entry for `key`.
+pub fn find(root: &Path, key: &CacheKey) -> Result<CacheEntry, EncodeError> {
+    read_manifest(&root.join(key.dir_name()))
+        .filter(|e| e.complete && e.key == *key)
+        .ok_or_else(|| "No complete cached render for these settings".into())
+}
+
+/// A complete entry opened for reading. Holds a shared lock on the entry's
+/// lock file until dropped, so `remove`, `evict` and a newer render's
+/// `commit` leave the entry alone meanwhile.
+pub struct CacheReader {
+    dir: PathBuf,
+    pub entry: CacheEntry,
+    _lock: File,
+}
+
+impl CacheReader {
+    /// Open the complete entry for `key`.
+    pub fn open(root: &Path, key: &CacheKey) -> Result<Self, EncodeError> {
+        let entry = find(root, key)?;
+        let dir   = root.join(key.dir_name());
+        let path  = dir.join(LOCK_FILE);
+        let lock  = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .and_then(|f| FileExt::try_lock_shared(&f).map(|()| f))
+            .map_err(|e| EncodeError::Io { message: format!("Failed to lock {}: {e}", path.display()) })?;
+        Ok(Self { dir, entry, _lock: lock })
+    }
+
+    /// Decompress frame `index`.
+    pub fn read_frame(&self, index: u64) -> Result<Vec<u8>, EncodeError> {
+        let path = frame_path(&self.dir, index);
+        let io   = |message: String| EncodeError::Io { message };
+        let file = File::open(&path).map_err(|e| io(format!("Failed to open {}: {e}", path.display())))?;
+        let len  = self.entry.input_format.frame_len(self.entry.key.width, self.entry.key.height);
+        let mut frame = Vec::with_capacity(len);
+        DeflateDecoder::new(BufReader::new(file))
+            .read_to_end(&mut frame)
+            .map_err(|e| io(format!("Failed to read {}: {e}", path.display())))?;
+        if frame.len() != len {
+            return Err(io(format!("Cached frame {} is corrupt", path.display())));
+        }
+        Ok(frame)
+    }
+}
+
+/// Whether a `CacheReader` holds the complete entry in `dir`.
+fn is_being_read(dir: &Path) -> bool {
+    match OpenOptions::new().write(true).open(dir.join(LOCK_FILE)) {
+        Ok(lock) => lock.try_lock_exclusive().is_err(),
+        Err(_)   => false,
+    }
+}
+
+/// Bump an entry's last-used time so eviction keeps it.
+pub fn touch(root: &Path, key: &CacheKey) {
+    let dir = root.join(key.dir_name());
+    if let Some(mut entry) = read_manifest(&dir) {
+        entry.last_used = now();
+        let _ = write_manifest(&dir, &entry);
+    }
+}
+
+/// Delete the entry for `key`, unless a re-encode is reading it.
+pub fn remove(root: &Path, key: &CacheKey) -> Result<(), EncodeError> {
+    let dir = root.join(key.dir_name());
+    if is_being_read(&dir) {
+        return Err("This cached render is being re-encoded".into());
+    }
+    let _ = std::fs::remove_dir_all(dir);
+    Ok(())
+}
+
+/// Remove the least recently used complete entries until the total is at most
+/// `limit`, along with abandoned incomplete ones (see `list`). Entries still
+/// being recorded or being read are never evicted.
+pub fn evict(root: &Path, limit: u64) {
+    let mut entries = list(root);
+    let mut total: u64 = entries.iter().map(|e| e.bytes).sum();
+    while total > limit {
+        let Some(pos) = entries.iter().rposition(|e| e.complete) else { break };
+        let entry = entries.remove(pos);
+        if remove(root, &entry.key).is_err() {
+            continue;
+        }
+        log::info!("Evicted cached render of {}", entry.key.graph_hash);
+        total -= entry.bytes;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> CacheKey {
+        CacheKey { graph_hash: "abc".into(), width: 2, height: 1, fps: 30, start: 0.0, end: 1.0 }
+    }
+
+    /// Record and commit a two-frame entry under a fresh root.
+    fn cached(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("shader-studio-cache-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let recorder = CacheRecorder::create(&root, key(), FrameFormat::Rgba8, DEFAULT_LIMIT, 1).unwrap();
+        let mut sink = recorder.tee(Box::new(|_: &[u8]| Ok(())));
+        sink(&[1; 8]).unwrap();
+        sink(&[2; 8]).unwrap();
+        drop(sink);
+        assert_eq!(recorder.commit(), None);
+        root
+    }
+
+    #[test]
+    fn reads_back_committed_frames() {
+        let root   = cached("read");
+        let reader = CacheReader::open(&root, &key()).unwrap();
+        assert_eq!(reader.entry.frames, 2);
+        assert_eq!(reader.read_frame(1).unwrap(), [2; 8]);
+        assert!(reader.read_frame(2).is_err());
+        drop(reader);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn entries_being_read_are_not_removed() {
+        let root   = cached("busy");
+        let reader = CacheReader::open(&root, &key()).unwrap();
+        assert!(remove(&root, &key()).is_err());
+        evict(&root, 0);
+        assert!(find(&root, &key()).is_ok());
+
+        drop(reader);
+        evict(&root, 0);
+        assert!(find(&root, &key()).is_err());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,316 @@
+//! Video codec settings for `start_ffmpeg_encode`.
+//!
+//! Frames always arrive as RGBA. Each codec has a table of default args,
+//! picking an output pixel format depending on whether the alpha channel
+//! should survive the encode; `EncoderSettings` then overrides individual
+//! options on top of those defaults.
+
+use serde::{Deserialize, Serialize};
+
+/// Codec ids accepted by `start_ffmpeg_encode` (GIF is handled separately).
+pub const VIDEO_CODECS: &[&str] = &["h264", "prores", "ffv1", "vp9", "av1", "qtrle", "png"];
+
+/// Codecs that can carry an alpha channel.
+pub const ALPHA_CODECS: &[&str] = &["prores", "ffv1", "vp9", "qtrle", "png"];
+
+/// Codecs with a `-crf` quality knob.
+const CRF_CODECS: &[&str] = &["h264", "vp9", "av1"];
+
+/// Encoder options layered over a codec's defaults. Every field but `codec`
+/// is optional; unset fields keep the default from `video_args`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncoderSettings {
+    /// One of `VIDEO_CODECS`, or "gif".
+    pub codec: String,
+    /// Constant-quality factor (h264, vp9, av1 only).
+    pub crf: Option<u32>,
+    /// Target bitrate, e.g. "8M". Replaces CRF unless `crf` is also set.
+    pub bitrate: Option<String>,
+    /// Encoder speed preset, e.g. "slow" for x264 or "6" for SVT-AV1.
+    pub preset: Option<String>,
+    /// Encoder profile, e.g. "high" for x264 or "3" for ProRes 422 HQ.
+    pub profile: Option<String>,
+    /// Output pixel format, overriding the alpha-dependent default.
+    pub pix_fmt: Option<String>,
+    /// Maximum frames between keyframes (`-g`).
+    pub keyframe_interval: Option<u32>,
+    /// Encoder tuning, e.g. "animation" for x264.
+    pub tune: Option<String>,
+    /// Extra args appended verbatim after the codec args.
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+}
+
+impl EncoderSettings {
+    /// Output args: the codec defaults with every set field applied on top.
+    pub fn video_args(&self, preserve_alpha: bool) -> Result<Vec<String>, String> {
+        let mut args = video_args(&self.codec, preserve_alpha)?;
+
+        if let Some(crf) = self.crf {
+            if !CRF_CODECS.contains(&self.codec.as_str()) {
+                return Err(format!("Codec {:?} has no CRF setting", self.codec));
+            }
+            set_arg(&mut args, "-crf", crf.to_string());
+        }
+        if let Some(bitrate) = &self.bitrate {
+            if self.crf.is_none() {
+                remove_arg(&mut args, "-crf");
+            }
+            set_arg(&mut args, "-b:v", bitrate.clone());
+        }
+        if let Some(preset) = &self.preset {
+            set_arg(&mut args, "-preset", preset.clone());
+        }
+        if let Some(profile) = &self.profile {
+            set_arg(&mut args, "-profile:v", profile.clone());
+        }
+        if let Some(pix_fmt) = &self.pix_fmt {
+            set_arg(&mut args, "-pix_fmt", pix_fmt.clone());
+        }
+        if let Some(interval) = self.keyframe_interval {
+            set_arg(&mut args, "-g", interval.to_string());
+        }
+        if let Some(tune) = &self.tune {
+            set_arg(&mut args, "-tune", tune.clone());
+        }
+        args.extend(self.extra_args.iter().cloned());
+        Ok(args)
+    }
+}
+
+/// The value following the last `flag` — for FFmpeg the last one given wins.
+pub fn arg_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    let i = args.iter().rposition(|a| a == flag)?;
+    args.get(i + 1).map(String::as_str)
+}
+
+/// Replace the value following `flag`, or append the pair if it isn't present.
+fn set_arg(args: &mut Vec<String>, flag: &str, value: String) {
+    match args.iter().position(|a| a == flag) {
+        Some(i) => args[i + 1] = value,
+        None    => args.extend([flag.to_string(), value]),
+    }
+}
+
+fn remove_arg(args: &mut Vec<String>, flag: &str) {
+    if let Some(i) = args.iter().position(|a| a == flag) {
+        args.drain(i..i + 2);
+    }
+}
+
+/// Default output args for `codec`. Fails for unknown codecs, or when alpha is
+/// requested from a codec that can't carry it.
+pub fn video_args(codec: &str, preserve_alpha: bool) -> Result<Vec<String>, String> {
+    if !VIDEO_CODECS.contains(&codec) {
+        return Err(format!(
+            "Unknown codec {codec:?} (expected {} or gif)",
+            VIDEO_CODECS.join(", ")
+        ));
+    }
+    if preserve_alpha && !ALPHA_CODECS.contains(&codec) {
+        return Err(format!(
+            "Codec {codec:?} can't preserve alpha (use one of {})",
+            ALPHA_CODECS.join(", ")
+        ));
+    }
+
+    let args: Vec<&str> = match (codec, preserve_alpha) {
+        ("prores", false) => vec![
+            "-c:v", "prores_ks",
+            "-profile:v", "3",         // ProRes 422 HQ
+            "-vendor", "apl0",
+            "-pix_fmt", "yuv422p10le",
+        ],
+        ("prores", true) => vec![
+            "-c:v", "prores_ks",
+            "-profile:v", "4",         // ProRes 4444
+            "-vendor", "apl0",
+            "-alpha_bits", "16",
+            "-pix_fmt", "yuva444p10le",
+        ],
+        ("ffv1", _) => vec![
+            "-c:v", "ffv1",
+            "-level", "3",
+            "-coder", "1",
+            "-context", "1",
+            "-pix_fmt", if preserve_alpha { "bgra" } else { "yuv420p" },
+        ],
+        ("vp9", _) => vec![
+            "-c:v", "libvpx-vp9",
+            "-crf", "30",
+            "-b:v", "0",               // constant quality
+            "-row-mt", "1",
+            "-deadline", "good",
+            "-pix_fmt", if preserve_alpha { "yuva420p" } else { "yuv420p" },
+            // Alt-ref frames aren't supported together with an alpha plane
+            "-auto-alt-ref", if preserve_alpha { "0" } else { "1" },
+        ],
+        ("av1", _) => vec![
+            "-c:v", "libsvtav1",
+            "-crf", "32",
+            "-preset", "8",
+            "-pix_fmt", "yuv420p",
+        ],
+        ("qtrle", _) => vec![             // QuickTime Animation
+            "-c:v", "qtrle",
+            "-pix_fmt", if preserve_alpha { "argb" } else { "rgb24" },
+        ],
+        ("png", _) => vec![               // PNG-in-MOV
+            "-c:v", "png",
+            "-pix_fmt", if preserve_alpha { "rgba" } else { "rgb24" },
+        ],
+        _ => vec![                        // h264
+            "-c:v", "libx264",
+            "-preset", "slow",
+            "-crf", "18",
+            "-pix_fmt", "yuv420p",
+        ],
+    };
+    Ok(args.iter().map(|a| a.to_string()).collect())
+}
+
+/// Pixel formats with 8 bits or fewer per component — too coarse for HDR.
+const EIGHT_BIT_PIX_FMTS: &[&str] = &[
+    "yuv420p", "yuv422p", "yuv444p", "yuva420p", "yuva444p", "nv12",
+    "rgb24", "bgr24", "rgba", "bgra", "argb", "abgr", "gbrp", "gbrap", "gray",
+];
+
+pub fn is_eight_bit(pix_fmt: &str) -> bool {
+    EIGHT_BIT_PIX_FMTS.contains(&pix_fmt)
+}
+
+/// Width and height multiples that `pix_fmt`'s chroma subsampling needs —
+/// FFmpeg refuses other sizes only once encoding starts.
+pub fn size_alignment(pix_fmt: &str) -> (u32, u32) {
+    if pix_fmt.starts_with("nv12") || pix_fmt.starts_with("nv21") {
+        return (2, 2);
+    }
+    let Some(layout) = ["yuvj", "yuva", "yuv"].iter().find_map(|p| pix_fmt.strip_prefix(p)) else {
+        return (1, 1);
+    };
+    match layout.get(..3) {
+        Some("420") => (2, 2),
+        Some("422") => (2, 1),
+        Some("440") => (1, 2),
+        Some("411") => (4, 1),
+        Some("410") => (4, 4),
+        _           => (1, 1),
+    }
+}
+
+/// Whether the codec's container takes `-movflags +faststart` (MP4/QuickTime).
+pub fn uses_faststart(codec: &str) -> bool {
+    matches!(codec, "h264" | "prores" | "qtrle" | "png")
+}
+
+/// FFmpeg encoder used for `codec` (including "gif").
+pub fn encoder_name(codec: &str) -> &'static str {
+    match codec {
+        "prores" => "prores_ks",
+        "ffv1"   => "ffv1",
+        "vp9"    => "libvpx-vp9",
+        "av1"    => "libsvtav1",
+        "qtrle"  => "qtrle",
+        "png"    => "png",
+        "gif"    => "gif",
+        _        => "libx264",
+    }
+}
+
+/// FFmpeg muxer for the container `codec` is normally written to.
+pub fn muxer_name(codec: &str) -> &'static str {
+    match codec {
+        "prores" | "qtrle" | "png" => "mov",
+        "ffv1"                     => "matroska",
+        "vp9" | "av1"              => "webm",
+        "gif"                      => "gif",
+        _                          => "mp4",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(codec: &str) -> EncoderSettings {
+        EncoderSettings { codec: codec.into(), ..Default::default() }
+    }
+
+    #[test]
+    fn rejects_unknown_codecs_and_impossible_alpha() {
+        assert!(video_args("mpeg2", false).is_err());
+        assert!(video_args("h264", true).is_err());
+        assert!(video_args("av1", true).is_err());
+        for codec in ALPHA_CODECS {
+            assert!(video_args(codec, true).is_ok(), "{codec}");
+        }
+    }
+
+    #[test]
+    fn alpha_picks_a_format_with_an_alpha_plane() {
+        for codec in ALPHA_CODECS {
+            let args    = video_args(codec, true).unwrap();
+            let pix_fmt = arg_value(&args, "-pix_fmt").unwrap();
+            assert!(pix_fmt.contains('a'), "{codec}: {pix_fmt}");
+        }
+        assert_eq!(arg_value(&video_args("qtrle", true).unwrap(), "-pix_fmt"), Some("argb"));
+        assert_eq!(arg_value(&video_args("png", true).unwrap(), "-pix_fmt"), Some("rgba"));
+    }
+
+    #[test]
+    fn overrides_replace_defaults() {
+        let args = EncoderSettings {
+            crf: Some(23),
+            preset: Some("fast".into()),
+            pix_fmt: Some("yuv444p".into()),
+            keyframe_interval: Some(48),
+            extra_args: vec!["-x264-params".into(), "aq-mode=3".into()],
+            ..settings("h264")
+        }.video_args(false).unwrap();
+        assert_eq!(arg_value(&args, "-crf"), Some("23"));
+        assert_eq!(arg_value(&args, "-preset"), Some("fast"));
+        assert_eq!(arg_value(&args, "-pix_fmt"), Some("yuv444p"));
+        assert_eq!(arg_value(&args, "-g"), Some("48"));
+        assert_eq!(args.iter().filter(|a| *a == "-crf").count(), 1);
+        assert_eq!(args[args.len() - 2..], ["-x264-params", "aq-mode=3"]);
+    }
+
+    #[test]
+    fn bitrate_replaces_crf_unless_both_are_set() {
+        let bitrate = EncoderSettings { bitrate: Some("8M".into()), ..settings("h264") };
+        let args = bitrate.video_args(false).unwrap();
+        assert_eq!(arg_value(&args, "-b:v"), Some("8M"));
+        assert_eq!(arg_value(&args, "-crf"), None);
+
+        let both = EncoderSettings { crf: Some(20), ..bitrate };
+        let args = both.video_args(false).unwrap();
+        assert_eq!(arg_value(&args, "-crf"), Some("20"));
+        assert_eq!(arg_value(&args, "-b:v"), Some("8M"));
+    }
+
+    #[test]
+    fn crf_only_for_codecs_that_have_it() {
+        let prores = EncoderSettings { crf: Some(20), ..settings("prores") };
+        assert!(prores.video_args(false).is_err());
+    }
+
+    #[test]
+    fn subsampled_formats_need_aligned_sizes() {
+        assert_eq!(size_alignment("yuv420p"), (2, 2));
+        assert_eq!(size_alignment("yuva420p"), (2, 2));
+        assert_eq!(size_alignment("yuv422p10le"), (2, 1));
+        assert_eq!(size_alignment("yuva444p10le"), (1, 1));
+        assert_eq!(size_alignment("nv12"), (2, 2));
+        assert_eq!(size_alignment("rgb24"), (1, 1));
+        assert_eq!(size_alignment("gbrap16le"), (1, 1));
+    }
+
+    #[test]
+    fn eight_bit_formats() {
+        assert!(is_eight_bit("yuv420p"));
+        assert!(is_eight_bit("argb"));
+        assert!(!is_eight_bit("yuv420p10le"));
+        assert!(!is_eight_bit("yuva444p10le"));
+    }
+}
@@ -0,0 +1,62 @@
+//! JSON files the app keeps between runs — settings, encoder presets and the
+//! render queue.
+
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::error::EncodeError;
+
+/// `name` inside the app config dir.
+pub fn config_path(app: &AppHandle, name: &str) -> Result<PathBuf, EncodeError> {
+    let dir = app.path().app_config_dir().map_err(|e| EncodeError::Io { message: e.to_string() })?;
+    Ok(dir.join(name))
+}
+
+/// Read `path`, or return the default if it doesn't exist yet.
+pub fn load<T: DeserializeOwned + Default>(path: &Path) -> Result<T, EncodeError> {
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let io = |message: String| EncodeError::Io { message };
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| io(format!("Failed to read {}: {e}", path.display())))?;
+    serde_json::from_str(&text).map_err(|e| io(format!("Invalid file {}: {e}", path.display())))
+}
+
+/// Write `value` to `path`, creating its directory if needed.
+pub fn save<T: Serialize>(path: &Path, value: &T) -> Result<(), EncodeError> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let text = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    std::fs::write(path, text)
+        .map_err(|e| EncodeError::Io { message: format!("Failed to write {}: {e}", path.display()) })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    #[test]
+    fn round_trips_and_defaults_when_missing() {
+        let dir  = std::env::temp_dir().join(format!("shader-studio-config-test-{}", std::process::id()));
+        let path = dir.join("nested").join("test.json");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let empty: BTreeMap<String, u32> = load(&path).unwrap();
+        assert!(empty.is_empty());
+        save(&path, &BTreeMap::from([("a".to_string(), 1_u32)])).unwrap();
+        let loaded: BTreeMap<String, u32> = load(&path).unwrap();
+        assert_eq!(loaded["a"], 1);
+        std::fs::write(&path, "{ not json").unwrap();
+        let invalid: Result<BTreeMap<String, u32>, _> = load(&path);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(invalid, Err(EncodeError::Io { .. })));
+    }
+}
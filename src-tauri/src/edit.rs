@@ -0,0 +1,459 @@
+//! Trim, concatenate and transcode existing exports.
+//!
+//! Each edit runs FFmpeg as a background job writing a temp file next to the
+//! output (see `atomic`), renamed into place once FFmpeg succeeds. Progress is
+//! read from `-progress pipe:1` and emitted as `edit-progress` events, with a
+//! final event carrying `done`, `cancelled` or `error`.
+//!
+//! Without `encoder`, trims and plain concatenations copy the streams — fast,
+//! but cuts land on keyframes and concatenated inputs must share codecs.
+//! Crossfades and transcodes always re-encode.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::atomic::{self, PendingOutputs};
+use crate::audio;
+use crate::codec::{self, EncoderSettings};
+use crate::error::EncodeError;
+use crate::progress::{self, FfmpegLog, LOG_TAIL_LINES};
+
+pub type EditJobId = u32;
+
+/// Event emitted for every progress block and once when a job ends.
+pub const EDIT_PROGRESS_EVENT: &str = "edit-progress";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrimOptions {
+    pub input: String,
+    pub output_path: String,
+    /// Time range to keep, in seconds.
+    pub start: f64,
+    pub end: f64,
+    /// Re-encode with these settings; copies the streams when unset.
+    pub encoder: Option<EncoderSettings>,
+    #[serde(default)]
+    pub preserve_alpha: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConcatOptions {
+    /// Exports joined in order; they must share resolution and frame rate.
+    pub inputs: Vec<String>,
+    pub output_path: String,
+    /// Crossfade between consecutive inputs, in seconds (0 = hard cut).
+    #[serde(default)]
+    pub crossfade: f64,
+    /// Re-encode with these settings; required for crossfades.
+    pub encoder: Option<EncoderSettings>,
+    #[serde(default)]
+    pub preserve_alpha: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscodeOptions {
+    pub input: String,
+    pub output_path: String,
+    pub encoder: EncoderSettings,
+    #[serde(default)]
+    pub preserve_alpha: bool,
+}
+
+/// Payload of `edit-progress`.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditProgress {
+    pub job_id: EditJobId,
+    /// 0–1, from FFmpeg's output time over the expected output length.
+    pub fraction: f64,
+    pub out_time_us: i64,
+    pub speed: f32,
+    /// Set on the final event of a successful job.
+    pub done: bool,
+    pub cancelled: bool,
+    pub error: Option<EncodeError>,
+}
+
+/// What `ffmpeg -i` reports about an input.
+struct MediaInfo {
+    duration: f64,
+    has_audio: bool,
+}
+
+/// FFmpeg args (without the output path) and the expected output length.
+pub struct EditCommand {
+    args: Vec<String>,
+    duration: f64,
+    /// Concat list file to delete once the job ends.
+    list_file: Option<PathBuf>,
+}
+
+/// Read duration and audio presence from `ffmpeg -i`'s stderr.
+fn media_info(ffmpeg: &Path, input: &str) -> Result<MediaInfo, EncodeError> {
+    if !Path::new(input).is_file() {
+        return Err(format!("Input not found: {input}").into());
+    }
+    // Exits non-zero ("no output file") but still prints the input summary
+    let output = Command::new(ffmpeg)
+        .args(["-hide_banner", "-i", input])
+        .output()
+        .map_err(|e| EncodeError::Io { message: format!("Failed to run FFmpeg: {e}") })?;
+    let log = String::from_utf8_lossy(&output.stderr);
+    let duration = log
+        .lines()
+        .find_map(|l| l.trim().strip_prefix("Duration:"))
+        .and_then(|rest| rest.split(',').next())
+        .and_then(|t| parse_timestamp(t.trim()))
+        .ok_or_else(|| format!("Couldn't read the duration of {input}"))?;
+    let has_audio = log.lines().any(|l| l.contains("Stream #") && l.contains("Audio:"));
+    Ok(MediaInfo { duration, has_audio })
+}
+
+/// `HH:MM:SS.ss` → seconds.
+fn parse_timestamp(text: &str) -> Option<f64> {
+    let mut parts = text.split(':');
+    let (h, m, s) = (parts.next()?, parts.next()?, parts.next()?);
+    Some(h.parse::<f64>().ok()? * 3600.0 + m.parse::<f64>().ok()? * 60.0 + s.parse::<f64>().ok()?)
+}
+
+/// Video and audio codec args for re-encoding to `output_path`.
+fn encode_args(encoder: &EncoderSettings, preserve_alpha: bool, output_path: &str) -> Result<Vec<String>, String> {
+    let mut args = encoder.video_args(preserve_alpha)?;
+    let audio_codec = audio::audio_codec_for(output_path);
+    args.extend(["-c:a".into(), audio_codec.into()]);
+    if audio_codec == "aac" {
+        args.extend(["-b:a".into(), "192k".into()]);
+    }
+    if codec::uses_faststart(&encoder.codec) {
+        args.extend(["-movflags".into(), "+faststart".into()]);
+    }
+    Ok(args)
+}
+
+fn base_args() -> Vec<String> {
+    ["-hide_banner", "-loglevel", "warning", "-nostats", "-progress", "pipe:1", "-y"]
+        .iter().map(|a| a.to_string()).collect()
+}
+
+impl TrimOptions {
+    pub fn command(&self, ffmpeg: &Path) -> Result<EditCommand, EncodeError> {
+        let info = media_info(ffmpeg, &self.input)?;
+        if self.start < 0.0 || self.start >= self.end {
+            return Err(format!("Invalid trim range {}–{}", self.start, self.end).into());
+        }
+        let end = self.end.min(info.duration);
+        if self.start >= end {
+            return Err(format!("Trim starts after the end of {} ({:.2}s)", self.input, info.duration).into());
+        }
+        let mut args = base_args();
+        args.extend([
+            "-ss".into(), self.start.to_string(),
+            "-i".into(), self.input.clone(),
+            "-t".into(), (end - self.start).to_string(),
+            "-map".into(), "0".into(),
+        ]);
+        match &self.encoder {
+            Some(encoder) => args.extend(encode_args(encoder, self.preserve_alpha, &self.output_path)?),
+            None          => args.extend(["-c".into(), "copy".into()]),
+        }
+        Ok(EditCommand { args, duration: end - self.start, list_file: None })
+    }
+}
+
+impl ConcatOptions {
+    pub fn command(&self, ffmpeg: &Path, job_id: EditJobId) -> Result<EditCommand, EncodeError> {
+        if self.inputs.len() < 2 {
+            return Err("Concatenation needs at least two inputs".into());
+        }
+        let infos = self.inputs
+            .iter()
+            .map(|input| media_info(ffmpeg, input))
+            .collect::<Result<Vec<_>, _>>()?;
+        let fade = self.crossfade;
+        if fade < 0.0 {
+            return Err("Crossfade length must not be negative".into());
+        }
+        if fade > 0.0 && infos.iter().any(|i| i.duration <= fade) {
+            return Err("Crossfade is longer than one of the inputs".into());
+        }
+        let duration = infos.iter().map(|i| i.duration).sum::<f64>() - fade * (infos.len() - 1) as f64;
+        let audio    = infos.iter().all(|i| i.has_audio);
+
+        let mut args = base_args();
+        let Some(encoder) = &self.encoder else {
+            if fade > 0.0 {
+                return Err("Crossfades need re-encoding; set an encoder".into());
+            }
+            // Stream copy through the concat demuxer
+            let list_file = std::env::temp_dir()
+                .join(format!("shader-studio-concat-{}-{job_id}.txt", std::process::id()));
+            let list: String = self.inputs
+                .iter()
+                .map(|input| {
+                    let path = std::fs::canonicalize(input).map_err(|e| format!("{input}: {e}"))?;
+                    let path = path.to_string_lossy().replace('\'', r"'\''");
+                    Ok(format!("file '{path}'\n"))
+                })
+                .collect::<Result<_, String>>()?;
+            std::fs::write(&list_file, list).map_err(|e| EncodeError::Io {
+                message: format!("Failed to write {}: {e}", list_file.display()),
+            })?;
+            args.extend([
+                "-f".into(), "concat".into(),
+                "-safe".into(), "0".into(),
+                "-i".into(), list_file.to_string_lossy().into_owned(),
+                "-c".into(), "copy".into(),
+            ]);
+            return Ok(EditCommand { args, duration, list_file: Some(list_file) });
+        };
+
+        for input in &self.inputs {
+            args.extend(["-i".into(), input.clone()]);
+        }
+        let graph = if fade > 0.0 {
+            crossfade_graph(&infos, fade, audio)
+        } else {
+            let streams: String = (0..infos.len())
+                .map(|i| if audio { format!("[{i}:v][{i}:a]") } else { format!("[{i}:v]") })
+                .collect();
+            let outputs = if audio { "[v][a]" } else { "[v]" };
+            format!("{streams}concat=n={}:v=1:a={}{outputs}", infos.len(), audio as u8)
+        };
+        args.extend(["-filter_complex".into(), graph, "-map".into(), "[v]".into()]);
+        if audio {
+            args.extend(["-map".into(), "[a]".into()]);
+        }
+        args.extend(encode_args(encoder, self.preserve_alpha, &self.output_path)?);
+        Ok(EditCommand { args, duration, list_file: None })
+    }
+}
+
+/// Chain `xfade` (and `acrossfade`) through every input, ending in `[v]`/`[a]`.
+fn crossfade_graph(infos: &[MediaInfo], fade: f64, audio: bool) -> String {
+    let mut parts  = Vec::new();
+    let mut video  = "[0:v]".to_string();
+    let mut sound  = "[0:a]".to_string();
+    let mut offset = 0.0;
+    for (i, prev) in (1..).zip(&infos[..infos.len() - 1]) {
+        offset += prev.duration - fade;
+        let last = i == infos.len() - 1;
+        let v_out = if last { "[v]".to_string() } else { format!("[v{i}]") };
+        parts.push(format!("{video}[{i}:v]xfade=transition=fade:duration={fade}:offset={offset}{v_out}"));
+        video = v_out;
+        if audio {
+            let a_out = if last { "[a]".to_string() } else { format!("[a{i}]") };
+            parts.push(format!("{sound}[{i}:a]acrossfade=d={fade}{a_out}"));
+            sound = a_out;
+        }
+    }
+    parts.join(";")
+}
+
+impl TranscodeOptions {
+    pub fn command(&self, ffmpeg: &Path) -> Result<EditCommand, EncodeError> {
+        let info = media_info(ffmpeg, &self.input)?;
+        let mut args = base_args();
+        args.extend(["-i".into(), self.input.clone(), "-map".into(), "0:v:0".into()]);
+        if info.has_audio {
+            args.extend(["-map".into(), "0:a?".into()]);
+        }
+        args.extend(encode_args(&self.encoder, self.preserve_alpha, &self.output_path)?);
+        Ok(EditCommand { args, duration: info.duration, list_file: None })
+    }
+}
+
+struct EditHandle {
+    child: Arc<Mutex<Child>>,
+    cancelled: Arc<AtomicBool>,
+    output_path: String,
+    temp_path: PathBuf,
+}
+
+/// Running edit jobs.
+#[derive(Default)]
+pub struct EditState {
+    jobs: Mutex<HashMap<EditJobId, EditHandle>>,
+    next_id: AtomicU32,
+}
+
+impl EditState {
+    pub fn next_id(&self) -> EditJobId {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Whether a running job is writing `output_path`.
+    pub fn is_writing(&self, output_path: &str) -> Result<bool, EncodeError> {
+        Ok(self.jobs.lock()?.values().any(|job| job.output_path == output_path))
+    }
+
+    /// Kill a job's FFmpeg; its monitor thread cleans up and reports `cancelled`.
+    pub fn cancel(&self, job_id: EditJobId) -> Result<(), EncodeError> {
+        let jobs = self.jobs.lock()?;
+        let job  = jobs.get(&job_id).ok_or_else(|| format!("No edit job {job_id}"))?;
+        job.cancelled.store(true, Ordering::Relaxed);
+        let _ = job.child.lock()?.kill();
+        Ok(())
+    }
+
+    /// Temp files of running jobs. Their monitor threads remove them, and their
+    /// `PendingOutputs` entries, once FFmpeg has exited.
+    pub fn temp_paths(&self) -> Vec<PathBuf> {
+        let Ok(jobs) = self.jobs.lock() else { return Vec::new() };
+        jobs.values().map(|job| job.temp_path.clone()).collect()
+    }
+
+    /// Kill every running job (window closed).
+    pub fn cancel_all(&self) {
+        let Ok(jobs) = self.jobs.lock() else { return };
+        for job in jobs.values() {
+            job.cancelled.store(true, Ordering::Relaxed);
+            if let Ok(mut child) = job.child.lock() {
+                let _ = child.kill();
+            }
+        }
+    }
+}
+
+/// Spawn FFmpeg for `command` writing to a temp file for `output_path`, and
+/// watch it on a background thread until it exits. Fails with `SessionBusy` if
+/// another edit job is writing `output_path`.
+pub fn spawn(
+    app: &AppHandle,
+    ffmpeg: &Path,
+    job_id: EditJobId,
+    command: EditCommand,
+    output_path: &str,
+) -> Result<(), EncodeError> {
+    let output    = PathBuf::from(output_path);
+    let temp_path = atomic::temp_path(&output, format!("edit-{job_id}"));
+    let pending   = app.state::<PendingOutputs>();
+
+    // Held until the job is registered so two jobs can't claim the same output
+    let edits    = app.state::<EditState>();
+    let mut jobs = edits.jobs.lock()?;
+    if jobs.values().any(|job| job.output_path == output_path) {
+        return Err(EncodeError::SessionBusy { output_path: output_path.to_string() });
+    }
+
+    let mut args = command.args;
+    args.push(temp_path.to_string_lossy().into_owned());
+    pending.add(&temp_path);
+    let mut child = Command::new(ffmpeg)
+        .args(&args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            pending.remove(&temp_path);
+            if let Some(list_file) = &command.list_file {
+                let _ = std::fs::remove_file(list_file);
+            }
+            EncodeError::Io { message: format!("Failed to spawn FFmpeg: {e}") }
+        })?;
+    let pipe_error = |name: &str| EncodeError::Io { message: format!("Failed to get FFmpeg {name}") };
+    let stdout     = child.stdout.take().ok_or_else(|| pipe_error("stdout"))?;
+    let stderr     = child.stderr.take().ok_or_else(|| pipe_error("stderr"))?;
+
+    let log       = FfmpegLog::default();
+    let log_read  = progress::spawn_log_reader(stderr, log.clone());
+    let child     = Arc::new(Mutex::new(child));
+    let cancelled = Arc::new(AtomicBool::new(false));
+    jobs.insert(job_id, EditHandle {
+        child: child.clone(),
+        cancelled: cancelled.clone(),
+        output_path: output_path.to_string(),
+        temp_path: temp_path.clone(),
+    });
+    drop(jobs);
+
+    let app = app.clone();
+    thread::spawn(move || {
+        let mut progress = EditProgress { job_id, ..Default::default() };
+        let total_us = command.duration * 1e6;
+        progress::read_progress(stdout, |block, end| {
+            progress.out_time_us = block.out_time_us;
+            progress.speed       = block.speed;
+            // The final event is sent once the output is in place
+            if !end {
+                progress.fraction = (block.out_time_us as f64 / total_us).clamp(0.0, 1.0);
+                let _ = app.emit(EDIT_PROGRESS_EVENT, progress.clone());
+            }
+        });
+
+        // stdout closes when FFmpeg exits
+        let status = child.lock().map_err(EncodeError::from).and_then(|mut c| {
+            c.wait().map_err(|e| EncodeError::Io { message: format!("FFmpeg wait error: {e}") })
+        });
+        let _ = log_read.join();
+        let result = match status {
+            _ if cancelled.load(Ordering::Relaxed) => Err(None),
+            Ok(s) if s.success() => atomic::commit(&temp_path, &output).map_err(Some),
+            Ok(s) => Err(Some(EncodeError::NonZeroExit { code: s.code(), log_tail: log.tail(LOG_TAIL_LINES) })),
+            Err(e) => Err(Some(e)),
+        };
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp_path);
+        }
+        if let Some(list_file) = &command.list_file {
+            let _ = std::fs::remove_file(list_file);
+        }
+        app.state::<PendingOutputs>().remove(&temp_path);
+        if let Ok(mut jobs) = app.state::<EditState>().jobs.lock() {
+            jobs.remove(&job_id);
+        }
+
+        match result {
+            Ok(())         => {
+                progress.fraction = 1.0;
+                progress.done     = true;
+            }
+            Err(None)      => progress.cancelled = true,
+            Err(Some(e))   => progress.error = Some(e),
+        }
+        let _ = app.emit(EDIT_PROGRESS_EVENT, progress);
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_timestamps() {
+        assert_eq!(parse_timestamp("00:00:05.50"), Some(5.5));
+        assert_eq!(parse_timestamp("01:02:03"), Some(3723.0));
+        assert_eq!(parse_timestamp("N/A"), None);
+        assert_eq!(parse_timestamp("00:05"), None);
+    }
+
+    #[test]
+    fn crossfades_chain_through_every_input() {
+        let info = |duration| MediaInfo { duration, has_audio: true };
+        let graph = crossfade_graph(&[info(4.0), info(3.0), info(5.0)], 1.0, true);
+        assert_eq!(graph, [
+            "[0:v][1:v]xfade=transition=fade:duration=1:offset=3[v1]",
+            "[0:a][1:a]acrossfade=d=1[a1]",
+            "[v1][2:v]xfade=transition=fade:duration=1:offset=5[v]",
+            "[a1][2:a]acrossfade=d=1[a]",
+        ].join(";"));
+    }
+
+    #[test]
+    fn crossfade_without_audio_has_no_audio_chain() {
+        let info = |duration| MediaInfo { duration, has_audio: false };
+        let graph = crossfade_graph(&[info(2.0), info(2.0)], 0.5, false);
+        assert_eq!(graph, "[0:v][1:v]xfade=transition=fade:duration=0.5:offset=1.5[v]");
+    }
+}
@@ -0,0 +1,353 @@
+//! FFmpeg command line for an encode session.
+//!
+//! `EncodeOptions` is what the frontend passes to `start_ffmpeg_encode`; the
+//! same options drive `preview_ffmpeg_command`, so the dry run prints exactly
+//! the args a real session would spawn FFmpeg with.
+
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::accumulate::MotionBlur;
+use crate::cache::CacheKey;
+use crate::audio::AudioTrack;
+use crate::codec::{self, EncoderSettings};
+use crate::filters::{self, FrameFilter};
+use crate::frame::{self, FrameFormat, HdrTransfer};
+use crate::gif::GifOptions;
+use crate::ordering::GapPolicy;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodeOptions {
+    pub output_path: String,
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    /// Codec and encoder overrides. Ignored when `preset` is set.
+    #[serde(default)]
+    pub encoder: EncoderSettings,
+    /// Name of a saved encoder preset to use instead of `encoder`.
+    pub preset: Option<String>,
+    /// Keep the RGBA alpha channel; rejected for codecs that can't carry it.
+    #[serde(default)]
+    pub preserve_alpha: bool,
+    /// Audio muxed in and trimmed to the rendered length.
+    pub audio: Option<AudioTrack>,
+    /// Palette, dithering, loop count and frame rate for "gif" output.
+    pub gif: Option<GifOptions>,
+    /// Filters applied to each `width`×`height` frame before encoding.
+    #[serde(default)]
+    pub filters: Vec<FrameFilter>,
+    /// Average several sub-frames into each encoded frame.
+    pub motion_blur: Option<MotionBlur>,
+    /// Layout of the frames sent with `send_frame_rgba`.
+    #[serde(default)]
+    pub input_format: FrameFormat,
+    /// Write BT.2020 HDR with this transfer function. `rgba32f` frames are
+    /// converted from scene-linear; integer frames must already be encoded with
+    /// it. Needs an output pixel format deeper than 8 bits.
+    pub hdr: Option<HdrTransfer>,
+    /// Output frames the frontend will send; enables the ETA in `get_encode_status`.
+    pub expected_frames: Option<u64>,
+    /// How frames that never arrive are handled (see `ordering`).
+    #[serde(default)]
+    pub gap_policy: GapPolicy,
+    /// Also store the frames in the frame cache under this key (see `cache`).
+    pub cache: Option<CacheKey>,
+}
+
+/// Codecs whose default 8-bit-or-less pixel format is raised for deep input.
+const DEEP_PIX_FMTS: &[(&str, bool, &str)] = &[
+    ("ffv1", false, "yuv444p12le"),
+    ("ffv1", true,  "gbrap16le"),
+    ("png",  false, "rgb48be"),
+    ("png",  true,  "rgba64be"),
+];
+
+impl EncodeOptions {
+    /// Size of the frames FFmpeg encodes, after `filters`.
+    pub fn output_size(&self) -> Result<(u32, u32), String> {
+        let (_, width, height) = filters::build_chain(&self.filters, self.width, self.height)?;
+        Ok((width, height))
+    }
+
+    /// Every FFmpeg arg after the program name.
+    pub fn ffmpeg_args(&self) -> Result<Vec<String>, String> {
+        self.ffmpeg_args_to(&self.output_path)
+    }
+
+    /// Same as `ffmpeg_args`, but FFmpeg writes to `output` instead of
+    /// `output_path` (the session's temp file; see `atomic`).
+    pub fn ffmpeg_args_to(&self, output: &str) -> Result<Vec<String>, String> {
+        let codec = self.encoder.codec.as_str();
+        if codec == "gif" && self.audio.is_some() {
+            return Err("GIF output can't carry an audio track".into());
+        }
+        // Build filter and codec-specific output args first so bad settings fail early
+        let (mut filter_chain, out_w, out_h) = filters::build_chain(&self.filters, self.width, self.height)?;
+        // Float input is scene-linear; convert after the user's filters
+        if self.input_format == FrameFormat::Rgba32f {
+            let conversion = match self.hdr {
+                Some(hdr) => hdr.linear_conversion_filter(self.preserve_alpha),
+                None      => frame::sdr_conversion_filter(self.preserve_alpha),
+            };
+            if !filter_chain.is_empty() {
+                filter_chain.push(',');
+            }
+            filter_chain.push_str(&conversion);
+        }
+        let deep = self.input_format != FrameFormat::Rgba8;
+        let codec_args = match codec {
+            "gif" if self.preserve_alpha => return Err("GIF output can't preserve alpha".into()),
+            "gif" => self.gif.clone().unwrap_or_default().output_args(&filter_chain)?,
+            _ => {
+                let mut encoder = self.encoder.clone();
+                // Keep the extra precision unless a pixel format was chosen explicitly
+                if deep && encoder.pix_fmt.is_none() {
+                    encoder.pix_fmt = DEEP_PIX_FMTS
+                        .iter()
+                        .find(|(c, alpha, _)| *c == codec && *alpha == self.preserve_alpha)
+                        .map(|(_, _, f)| f.to_string());
+                }
+                encoder.video_args(self.preserve_alpha)?
+            }
+        };
+        if let Some(pix_fmt) = codec::arg_value(&codec_args, "-pix_fmt") {
+            let (align_w, align_h) = codec::size_alignment(pix_fmt);
+            if out_w % align_w != 0 || out_h % align_h != 0 {
+                return Err(format!(
+                    "{codec} is written as {pix_fmt}, which needs a size divisible by \
+                     {align_w}x{align_h}, but frames reach it at {out_w}x{out_h}; \
+                     crop, scale or pad to an even size"
+                ));
+            }
+        }
+        if self.hdr.is_some() {
+            if codec == "gif" {
+                return Err("GIF output can't carry HDR metadata".into());
+            }
+            let pix_fmt = codec::arg_value(&codec_args, "-pix_fmt");
+            if let Some(pix_fmt) = pix_fmt.filter(|f| codec::is_eight_bit(f)) {
+                return Err(format!(
+                    "HDR output needs more than 8 bits per component, but {codec} would be \
+                     written as {pix_fmt}; choose a pixel format such as yuv420p10le"
+                ));
+            }
+        }
+
+        let mut args: Vec<String> = [
+            "-hide_banner",
+            "-loglevel", "warning",    // keep stderr to warnings/errors
+            "-nostats",
+            "-progress", "pipe:1",     // machine-readable progress on stdout
+            "-y",                      // overwrite
+            "-f", "rawvideo",
+            "-vcodec", "rawvideo",
+            "-pix_fmt", self.input_format.ffmpeg_pix_fmt(),
+            "-s", &format!("{}x{}", self.width, self.height),
+            "-r", &self.fps.to_string(),
+            "-i", "pipe:0",            // read frames from stdin
+        ].iter().map(|a| a.to_string()).collect();
+
+        if let Some(audio) = &self.audio {
+            args.extend(audio.input_args()?);
+            let video_secs = self.expected_frames
+                .filter(|_| self.fps > 0)
+                .map(|n| n as f64 / self.fps as f64);
+            args.extend(audio.output_args(output, video_secs)?);
+        }
+        // GIF folds the chain into its own filter graph
+        if codec != "gif" && !filter_chain.is_empty() {
+            args.extend(["-vf".into(), filter_chain]);
+        }
+        args.extend(codec_args);
+        if let Some(hdr) = self.hdr {
+            args.extend(hdr.output_args());
+        }
+        // faststart only applies to MP4/QuickTime containers
+        if codec::uses_faststart(codec) {
+            args.extend(["-movflags".into(), "+faststart".into()]);
+        }
+        args.push(output.to_string());
+        Ok(args)
+    }
+}
+
+/// Render `program args…` as a single shell-style command line, quoting
+/// every arg with characters outside a shell-safe set — filter graphs carry
+/// `;`, `[]` and `()`.
+pub fn command_line(program: &Path, args: &[String]) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "_@%+=:,./-".contains(c);
+    std::iter::once(program.to_string_lossy().into_owned())
+        .chain(args.iter().cloned())
+        .map(|a| {
+            if a.is_empty() || !a.chars().all(safe) {
+                format!("'{}'", a.replace('\'', r"'\''"))
+            } else {
+                a
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::arg_value;
+
+    fn options(codec: &str) -> EncodeOptions {
+        EncodeOptions {
+            output_path: "out.mp4".into(),
+            width: 640,
+            height: 480,
+            fps: 30,
+            encoder: EncoderSettings { codec: codec.into(), ..Default::default() },
+            preset: None,
+            preserve_alpha: false,
+            audio: None,
+            gif: None,
+            filters: Vec::new(),
+            motion_blur: None,
+            input_format: FrameFormat::Rgba8,
+            hdr: None,
+            expected_frames: None,
+            gap_policy: GapPolicy::default(),
+            cache: None,
+        }
+    }
+
+    #[test]
+    fn deep_input_raises_default_pix_fmts() {
+        for (codec, alpha, pix_fmt) in DEEP_PIX_FMTS {
+            let deep = EncodeOptions {
+                preserve_alpha: *alpha,
+                input_format: FrameFormat::Rgba16,
+                ..options(codec)
+            };
+            assert_eq!(arg_value(&deep.ffmpeg_args().unwrap(), "-pix_fmt"), Some(*pix_fmt), "{codec}");
+        }
+        // An explicit choice wins
+        let mut chosen = EncodeOptions { input_format: FrameFormat::Rgba16, ..options("ffv1") };
+        chosen.encoder.pix_fmt = Some("yuv420p".into());
+        assert_eq!(arg_value(&chosen.ffmpeg_args().unwrap(), "-pix_fmt"), Some("yuv420p"));
+    }
+
+    #[test]
+    fn hdr_rejects_eight_bit_output() {
+        let hdr = EncodeOptions { hdr: Some(HdrTransfer::Pq), ..options("h264") };
+        assert!(hdr.ffmpeg_args().unwrap_err().contains("yuv420p"));
+        let gif = EncodeOptions { hdr: Some(HdrTransfer::Pq), ..options("gif") };
+        assert!(gif.ffmpeg_args().is_err());
+
+        let mut ten_bit = hdr.clone();
+        ten_bit.encoder.pix_fmt = Some("yuv420p10le".into());
+        let args = ten_bit.ffmpeg_args().unwrap();
+        assert_eq!(arg_value(&args, "-color_trc"), Some("smpte2084"));
+    }
+
+    #[test]
+    fn float_hdr_converts_alpha_in_float() {
+        let hdr = EncodeOptions {
+            preserve_alpha: true,
+            input_format: FrameFormat::Rgba32f,
+            hdr: Some(HdrTransfer::Hlg),
+            ..options("prores")
+        };
+        let args = hdr.ffmpeg_args().unwrap();
+        assert!(arg_value(&args, "-vf").unwrap().starts_with("format=gbrapf32le,"));
+    }
+
+    #[test]
+    fn float_sdr_encodes_the_linear_input() {
+        let sdr = EncodeOptions {
+            input_format: FrameFormat::Rgba32f,
+            filters: vec![FrameFilter::VFlip],
+            ..options("h264")
+        };
+        let args = sdr.ffmpeg_args().unwrap();
+        assert_eq!(arg_value(&args, "-vf"), Some(&*format!("vflip,{}", frame::sdr_conversion_filter(false))));
+        assert!(arg_value(&args, "-vf").unwrap().contains("transferin=linear:primariesin=709:transfer=709"));
+        // Integer input is already sRGB-encoded
+        assert_eq!(arg_value(&options("h264").ffmpeg_args().unwrap(), "-vf"), None);
+    }
+
+    #[test]
+    fn rejects_sizes_the_pixel_format_cant_hold() {
+        let crop = |width, height| vec![FrameFilter::Crop { x: 0, y: 0, width, height }];
+        let odd  = EncodeOptions { filters: crop(639, 480), ..options("h264") };
+        assert!(odd.ffmpeg_args().unwrap_err().contains("639x480"));
+        // 4:2:2 only halves the width
+        let prores = EncodeOptions { filters: crop(640, 479), ..options("prores") };
+        assert!(prores.ffmpeg_args().is_ok());
+        let prores = EncodeOptions { filters: crop(639, 480), ..prores };
+        assert!(prores.ffmpeg_args().is_err());
+        // RGB formats take any size; so does an odd source once padded
+        let png = EncodeOptions { width: 641, height: 481, ..options("png") };
+        assert!(png.ffmpeg_args().is_ok());
+        let padded = EncodeOptions {
+            filters: vec![FrameFilter::Pad { aspect: 4.0 / 3.0, color: "black".into() }],
+            ..EncodeOptions { width: 641, height: 481, ..options("h264") }
+        };
+        assert_eq!(padded.output_size().unwrap(), (642, 482));
+        assert!(padded.ffmpeg_args().is_ok());
+    }
+
+    #[test]
+    fn writes_to_the_given_output() {
+        let args = options("h264").ffmpeg_args_to("tmp.mp4").unwrap();
+        assert_eq!(args.last().map(String::as_str), Some("tmp.mp4"));
+        assert_eq!(args[args.len() - 3..args.len() - 1], ["-movflags", "+faststart"]);
+    }
+
+    #[test]
+    fn rejects_gif_conflicts() {
+        let audio = AudioTrack {
+            path: "a.wav".into(),
+            offset: 0.0,
+            duration: None,
+            fade_in: 0.0,
+            fade_out: 0.0,
+            gain_db: 0.0,
+        };
+        let with_audio = EncodeOptions { audio: Some(audio), ..options("gif") };
+        assert!(with_audio.ffmpeg_args().is_err());
+        let with_alpha = EncodeOptions { preserve_alpha: true, ..options("gif") };
+        assert!(with_alpha.ffmpeg_args().is_err());
+        let with_alpha = EncodeOptions { preserve_alpha: true, ..options("h264") };
+        assert!(with_alpha.ffmpeg_args().is_err());
+    }
+
+    #[test]
+    fn gif_folds_filters_into_its_graph() {
+        let gif  = EncodeOptions { filters: vec![FrameFilter::VFlip], ..options("gif") };
+        let args = gif.ffmpeg_args().unwrap();
+        assert!(!args.iter().any(|a| a == "-vf"));
+        assert!(args.iter().any(|a| a.starts_with("[0:v]vflip,split")));
+    }
+
+    #[test]
+    fn command_line_quotes_filter_graphs() {
+        let gif = EncodeOptions {
+            output_path: "my clip.gif".into(),
+            filters: vec![FrameFilter::Pad { aspect: 1.0, color: "black".into() }],
+            ..options("gif")
+        };
+        let line = command_line(Path::new("ffmpeg"), &gif.ffmpeg_args().unwrap());
+        assert!(line.starts_with("ffmpeg -hide_banner -loglevel warning "), "{line}");
+        assert!(line.contains(" -s 640x480 -r 30 -i pipe:0 "), "{line}");
+        assert!(line.contains(
+            " '[0:v]pad=640:640:(ow-iw)/2:(oh-ih)/2:color=black,split[a][b];\
+             [a]palettegen=max_colors=256:stats_mode=diff[p];\
+             [b][p]paletteuse=dither=sierra2_4a:diff_mode=rectangle' "
+        ), "{line}");
+        assert!(line.ends_with(" 'my clip.gif'"), "{line}");
+    }
+
+    #[test]
+    fn command_line_escapes_single_quotes() {
+        let line = command_line(Path::new("/opt/ffmpeg"), &["it's".into(), String::new()]);
+        assert_eq!(line, r"/opt/ffmpeg 'it'\''s' ''");
+    }
+}
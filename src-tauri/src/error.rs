@@ -0,0 +1,103 @@
+//! Error type returned by the Tauri commands.
+//!
+//! Serialised as `{ "kind": "...", ...fields }` so the frontend can switch on
+//! `kind` and render the structured fields instead of matching message text.
+
+use std::fmt;
+use std::sync::PoisonError;
+
+use serde::Serialize;
+
+use crate::SessionId;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum EncodeError {
+    /// No usable FFmpeg binary; `path` is set when a configured one is missing.
+    FfmpegNotFound { path: Option<String> },
+    DownloadFailed { message: String },
+    /// Another session is already writing to `output_path`.
+    SessionBusy { output_path: String },
+    NoSession { session_id: SessionId },
+    FrameSizeMismatch { got: usize, expected: usize },
+    /// Frame `index` never arrived and the session's gap policy is `fail`
+    /// (or there was no earlier frame to repeat).
+    MissingFrame { index: u64 },
+    /// Writing to the encoder failed — usually because FFmpeg died.
+    PipeBroken { message: String, log_tail: String },
+    /// FFmpeg exited unsuccessfully (`code` is None if it was killed by a signal).
+    NonZeroExit { code: Option<i32>, log_tail: String },
+    /// The output directory is missing or can't be written to.
+    OutputNotWritable { path: String, message: String },
+    /// The estimated output plus the free-space reserve doesn't fit.
+    /// `kept_path` is where a session stopped by the watchdog left its
+    /// truncated output, if it was kept.
+    InsufficientSpace { path: String, required_bytes: u64, free_bytes: u64, kept_path: Option<String> },
+    LockPoisoned,
+    /// Rejected options or arguments.
+    Invalid { message: String },
+    /// Spawning, waiting on or reading/writing files failed.
+    Io { message: String },
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodeError::FfmpegNotFound { path: Some(p) } => write!(f, "Configured FFmpeg not found: {p}"),
+            EncodeError::FfmpegNotFound { path: None }    => write!(f, "FFmpeg not found"),
+            EncodeError::DownloadFailed { message }       => write!(f, "FFmpeg download failed: {message}"),
+            EncodeError::SessionBusy { output_path }      => write!(f, "A session is already writing to {output_path}"),
+            EncodeError::NoSession { session_id }         => write!(f, "No active session {session_id}"),
+            EncodeError::FrameSizeMismatch { got, expected } => {
+                write!(f, "Frame size mismatch: got {got} bytes, expected {expected}")
+            }
+            EncodeError::MissingFrame { index }           => write!(f, "Frame {index} never arrived"),
+            EncodeError::PipeBroken { message, .. }       => write!(f, "{message}"),
+            EncodeError::NonZeroExit { code, .. }         => write!(f, "FFmpeg exited with code {code:?}"),
+            EncodeError::OutputNotWritable { path, message } => write!(f, "Can't write {path}: {message}"),
+            EncodeError::InsufficientSpace { path, required_bytes, free_bytes, kept_path } => {
+                write!(
+                    f,
+                    "Not enough space for {path}: needs about {} MiB, {} MiB free",
+                    required_bytes / (1024 * 1024), free_bytes / (1024 * 1024),
+                )?;
+                match kept_path {
+                    Some(kept) => write!(f, "; the truncated output was kept at {kept}"),
+                    None       => Ok(()),
+                }
+            }
+            EncodeError::LockPoisoned                     => write!(f, "Session state lock poisoned"),
+            EncodeError::Invalid { message }              => write!(f, "{message}"),
+            EncodeError::Io { message }                   => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+impl<T> From<PoisonError<T>> for EncodeError {
+    fn from(_: PoisonError<T>) -> Self {
+        EncodeError::LockPoisoned
+    }
+}
+
+/// Plain messages are reserved for option/argument validation. Helpers that
+/// touch files or processes return `EncodeError` themselves (`Io`,
+/// `OutputNotWritable`, …) so failures keep their kind.
+impl From<String> for EncodeError {
+    fn from(message: String) -> Self {
+        EncodeError::Invalid { message }
+    }
+}
+
+impl From<&str> for EncodeError {
+    fn from(message: &str) -> Self {
+        EncodeError::Invalid { message: message.into() }
+    }
+}
+
+impl From<std::io::Error> for EncodeError {
+    fn from(e: std::io::Error) -> Self {
+        EncodeError::Io { message: e.to_string() }
+    }
+}
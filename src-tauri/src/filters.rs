@@ -0,0 +1,178 @@
+//! Typed video filter chain applied by FFmpeg before encoding.
+//!
+//! The chain is validated in Rust — each step checks its input size and
+//! reports the size it produces — so a bad crop or a zero-size scale fails
+//! in `start_ffmpeg_encode` rather than as an FFmpeg exit code. Frames can
+//! then be sent straight from the GL read-back at the internal render size.
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScaleKernel {
+    Bilinear,
+    Bicubic,
+    #[default]
+    Lanczos,
+    /// Box average — the natural choice for integer supersample factors.
+    Area,
+    Gauss,
+}
+
+impl ScaleKernel {
+    fn flag(self) -> &'static str {
+        match self {
+            ScaleKernel::Bilinear => "bilinear",
+            ScaleKernel::Bicubic  => "bicubic",
+            ScaleKernel::Lanczos  => "lanczos",
+            ScaleKernel::Area     => "area",
+            ScaleKernel::Gauss    => "gauss",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum FrameFilter {
+    /// Flip bottom-up GL read-back into top-down order.
+    VFlip,
+    /// Resample to `width`×`height`, e.g. to bring a supersampled render down.
+    Scale {
+        width: u32,
+        height: u32,
+        #[serde(default)]
+        kernel: ScaleKernel,
+    },
+    /// Letterbox/pillarbox to `aspect` (width / height), centring the frame.
+    Pad {
+        aspect: f64,
+        /// FFmpeg colour, e.g. "black" or "#202020". Defaults to black.
+        #[serde(default = "default_pad_color")]
+        color: String,
+    },
+    /// Keep the `width`×`height` region whose top-left corner is at `x`,`y`.
+    Crop { x: u32, y: u32, width: u32, height: u32 },
+}
+
+fn default_pad_color() -> String { "black".into() }
+
+/// Validate `chain` for frames of `width`×`height` and return the FFmpeg
+/// filter string (empty if there are no filters) and the output size.
+pub fn build_chain(
+    chain: &[FrameFilter],
+    width: u32,
+    height: u32,
+) -> Result<(String, u32, u32), String> {
+    if width == 0 || height == 0 {
+        return Err(format!("Frame size must be non-zero, got {width}x{height}"));
+    }
+    let (mut w, mut h) = (width, height);
+    let mut parts = Vec::with_capacity(chain.len());
+
+    for filter in chain {
+        match filter {
+            FrameFilter::VFlip => parts.push("vflip".to_string()),
+            FrameFilter::Scale { width, height, kernel } => {
+                if *width == 0 || *height == 0 {
+                    return Err(format!("Scale size must be non-zero, got {width}x{height}"));
+                }
+                parts.push(format!("scale={width}:{height}:flags={}", kernel.flag()));
+                (w, h) = (*width, *height);
+            }
+            FrameFilter::Pad { aspect, color } => {
+                if !aspect.is_finite() || *aspect <= 0.0 {
+                    return Err(format!("Pad aspect must be positive, got {aspect}"));
+                }
+                // Only allow plain colour names / hex so the value can't escape the filter
+                if color.is_empty() || !color.chars().all(|c| c.is_ascii_alphanumeric() || c == '#') {
+                    return Err(format!("Invalid pad colour {color:?}"));
+                }
+                let (pw, ph) = if (w as f64 / h as f64) < *aspect {
+                    (h as f64 * aspect, h as f64)
+                } else {
+                    (w as f64, w as f64 / aspect)
+                };
+                if pw.max(ph).round() >= u32::MAX as f64 {
+                    return Err(format!("Pad aspect {aspect} is too extreme for {w}x{h}"));
+                }
+                // Both sides even, including a side that isn't padded
+                let (pw, ph) = (even((pw.round() as u32).max(w)), even((ph.round() as u32).max(h)));
+                parts.push(format!("pad={pw}:{ph}:(ow-iw)/2:(oh-ih)/2:color={color}"));
+                (w, h) = (pw, ph);
+            }
+            FrameFilter::Crop { x, y, width, height } => {
+                // u64 so huge values from the frontend can't overflow past the check
+                let fits = |start: u32, len: u32, limit: u32| start as u64 + len as u64 <= limit as u64;
+                if *width == 0 || *height == 0 || !fits(*x, *width, w) || !fits(*y, *height, h) {
+                    return Err(format!(
+                        "Crop {width}x{height}+{x}+{y} doesn't fit inside {w}x{h}"
+                    ));
+                }
+                parts.push(format!("crop={width}:{height}:{x}:{y}"));
+                (w, h) = (*width, *height);
+            }
+        }
+    }
+    Ok((parts.join(","), w, h))
+}
+
+/// Round up to the next even number — chroma-subsampled formats need even sizes.
+fn even(v: u32) -> u32 {
+    v + (v & 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_chain_keeps_the_size() {
+        assert_eq!(build_chain(&[], 640, 480).unwrap(), (String::new(), 640, 480));
+        assert!(build_chain(&[], 0, 480).is_err());
+        assert!(build_chain(&[FrameFilter::VFlip], 640, 0).is_err());
+    }
+
+    #[test]
+    fn chains_filters_and_tracks_the_size() {
+        let chain = [
+            FrameFilter::VFlip,
+            FrameFilter::Scale { width: 1920, height: 1080, kernel: ScaleKernel::Area },
+            FrameFilter::Crop { x: 0, y: 60, width: 1920, height: 960 },
+        ];
+        let (filter, w, h) = build_chain(&chain, 3840, 2160).unwrap();
+        assert_eq!(filter, "vflip,scale=1920:1080:flags=area,crop=1920:960:0:60");
+        assert_eq!((w, h), (1920, 960));
+    }
+
+    #[test]
+    fn pads_to_aspect_with_even_sizes() {
+        // 4:3 → 16:9 pillarboxes; odd sides round up to even, padded or not
+        let pad = |aspect| FrameFilter::Pad { aspect, color: "black".into() };
+        let (filter, w, h) = build_chain(&[pad(16.0 / 9.0)], 1440, 1080).unwrap();
+        assert_eq!(filter, "pad=1920:1080:(ow-iw)/2:(oh-ih)/2:color=black");
+        assert_eq!((w, h), (1920, 1080));
+        let (_, w, h) = build_chain(&[pad(1.0)], 1001, 500).unwrap();
+        assert_eq!((w, h), (1002, 1002));
+        let (filter, w, h) = build_chain(&[pad(16.0 / 9.0)], 1920, 1081).unwrap();
+        assert_eq!(filter, "pad=1922:1082:(ow-iw)/2:(oh-ih)/2:color=black");
+        assert_eq!((w, h), (1922, 1082));
+    }
+
+    #[test]
+    fn rejects_bad_filters() {
+        let cases = [
+            FrameFilter::Scale { width: 0, height: 10, kernel: ScaleKernel::default() },
+            FrameFilter::Pad { aspect: 0.0, color: "black".into() },
+            FrameFilter::Pad { aspect: f64::NAN, color: "black".into() },
+            FrameFilter::Pad { aspect: 1.0, color: "black:x=0".into() },
+            FrameFilter::Crop { x: 10, y: 0, width: 100, height: 100 },
+            FrameFilter::Crop { x: 0, y: 0, width: 0, height: 100 },
+            FrameFilter::Crop { x: u32::MAX, y: 0, width: 2, height: 100 },
+            FrameFilter::Crop { x: 0, y: 1, width: 100, height: u32::MAX },
+            FrameFilter::Pad { aspect: 1e-12, color: "black".into() },
+        ];
+        for filter in cases {
+            assert!(build_chain(std::slice::from_ref(&filter), 100, 100).is_err(), "{filter:?}");
+        }
+    }
+}
@@ -0,0 +1,237 @@
+//! Frame input formats accepted by `send_frame_rgba`.
+//!
+//! Besides 8-bit RGBA, frames can come from 16-bit integer or 32-bit float
+//! render targets so gradients and HDR values survive to the encoder. Samples
+//! are little-endian, as produced by `Uint16Array`/`Float32Array` read-backs.
+//! Integer formats are display-referred (sRGB-encoded); float frames are
+//! scene-linear BT.709.
+//!
+//! Float frames are converted by FFmpeg's `zscale` (which needs an FFmpeg
+//! built with zimg): to the BT.709 transfer function for SDR output, or to
+//! PQ/HLG and BT.2020 for HDR output. Integer frames are passed through as
+//! they are, so for HDR they must already be PQ/HLG-encoded BT.2020.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrameFormat {
+    #[default]
+    Rgba8,
+    Rgba16,
+    Rgba32f,
+}
+
+impl FrameFormat {
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            FrameFormat::Rgba8   => 4,
+            FrameFormat::Rgba16  => 8,
+            FrameFormat::Rgba32f => 16,
+        }
+    }
+
+    /// Expected byte length of one `width`×`height` frame.
+    pub fn frame_len(self, width: u32, height: u32) -> usize {
+        width as usize * height as usize * self.bytes_per_pixel()
+    }
+
+    /// FFmpeg rawvideo pixel format for this input.
+    pub fn ffmpeg_pix_fmt(self) -> &'static str {
+        match self {
+            FrameFormat::Rgba8   => "rgba",
+            FrameFormat::Rgba16  => "rgba64le",
+            FrameFormat::Rgba32f => "rgbaf32le",
+        }
+    }
+
+    /// Samples as 16-bit sRGB-encoded integers (float input is encoded and clamped).
+    pub fn to_rgba16(self, data: &[u8]) -> Vec<u16> {
+        match self {
+            FrameFormat::Rgba8  => data.iter().map(|&v| v as u16 * 257).collect(),
+            FrameFormat::Rgba16 => data
+                .chunks_exact(2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                .collect(),
+            FrameFormat::Rgba32f => f32_samples(data)
+                .enumerate()
+                .map(|(i, v)| {
+                    let v = if i % 4 == 3 { v } else { linear_to_srgb(v) };
+                    (v.clamp(0.0, 1.0) * 65535.0).round() as u16
+                })
+                .collect(),
+        }
+    }
+
+    /// Samples as linear floats; integer colour channels are decoded from sRGB.
+    pub fn to_linear_f32(self, data: &[u8]) -> Vec<f32> {
+        match self {
+            FrameFormat::Rgba8 => data
+                .iter()
+                .enumerate()
+                .map(|(i, &v)| {
+                    let v = v as f32 / 255.0;
+                    if i % 4 == 3 { v } else { srgb_to_linear(v) }
+                })
+                .collect(),
+            FrameFormat::Rgba16 => self
+                .to_rgba16(data)
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| {
+                    let v = v as f32 / 65535.0;
+                    if i % 4 == 3 { v } else { srgb_to_linear(v) }
+                })
+                .collect(),
+            FrameFormat::Rgba32f => f32_samples(data).collect(),
+        }
+    }
+
+    /// Write one linear RGBA pixel (alpha 0–1) into `out` in this layout — the
+    /// inverse of `to_linear_f32`. Integer formats are sRGB-encoded and clamped.
+    pub fn write_linear_pixel(self, px: [f32; 4], out: &mut [u8]) {
+        let encoded = |i: usize| {
+            let v = if i == 3 { px[3] } else { linear_to_srgb(px[i]) };
+            v.clamp(0.0, 1.0)
+        };
+        match self {
+            FrameFormat::Rgba8 => {
+                for (i, o) in out.iter_mut().enumerate().take(4) {
+                    *o = (encoded(i) * 255.0).round() as u8;
+                }
+            }
+            FrameFormat::Rgba16 => {
+                for (i, o) in out.chunks_exact_mut(2).enumerate().take(4) {
+                    o.copy_from_slice(&((encoded(i) * 65535.0).round() as u16).to_le_bytes());
+                }
+            }
+            FrameFormat::Rgba32f => {
+                for (v, o) in px.iter().zip(out.chunks_exact_mut(4)) {
+                    o.copy_from_slice(&v.to_le_bytes());
+                }
+            }
+        }
+    }
+}
+
+fn f32_samples(data: &[u8]) -> impl Iterator<Item = f32> + '_ {
+    data.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.003_130_8 { c * 12.92 } else { 1.055 * c.max(0.0).powf(1.0 / 2.4) - 0.055 }
+}
+
+/// Transfer function tagged on HDR output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HdrTransfer {
+    /// SMPTE ST 2084 perceptual quantiser.
+    Pq,
+    /// ARIB STD-B67 hybrid log-gamma.
+    Hlg,
+}
+
+impl HdrTransfer {
+    fn trc(self) -> &'static str {
+        match self {
+            HdrTransfer::Pq  => "smpte2084",
+            HdrTransfer::Hlg => "arib-std-b67",
+        }
+    }
+
+    /// BT.2020 colour metadata args for the output stream.
+    pub fn output_args(self) -> Vec<String> {
+        ["-color_primaries", "bt2020", "-color_trc", self.trc(), "-colorspace", "bt2020nc"]
+            .iter().map(|a| a.to_string()).collect()
+    }
+
+    /// Filters converting scene-linear BT.709 float frames to this transfer
+    /// function and BT.2020, staying in float until zscale. Linear 1.0 lands on
+    /// zscale's nominal peak of 100 cd/m². With `alpha` the planar float format
+    /// keeps an alpha plane.
+    pub fn linear_conversion_filter(self, alpha: bool) -> String {
+        format!(
+            "format={},zscale=transferin=linear:primariesin=709:transfer={}:primaries=2020:matrix=2020_ncl",
+            float_planes(alpha),
+            self.trc(),
+        )
+    }
+}
+
+/// Filters encoding scene-linear BT.709 float frames with the BT.709 transfer
+/// function for SDR output, like `HdrTransfer::linear_conversion_filter`.
+pub fn sdr_conversion_filter(alpha: bool) -> String {
+    format!(
+        "format={},zscale=transferin=linear:primariesin=709:transfer=709:primaries=709:matrix=709",
+        float_planes(alpha),
+    )
+}
+
+/// Planar float format zscale converts in, with or without an alpha plane.
+fn float_planes(alpha: bool) -> &'static str {
+    if alpha { "gbrapf32le" } else { "gbrpf32le" }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn f32_bytes(samples: &[f32]) -> Vec<u8> {
+        samples.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn frame_lengths() {
+        assert_eq!(FrameFormat::Rgba8.frame_len(4, 2), 32);
+        assert_eq!(FrameFormat::Rgba16.frame_len(4, 2), 64);
+        assert_eq!(FrameFormat::Rgba32f.frame_len(4, 2), 128);
+    }
+
+    #[test]
+    fn widens_to_sixteen_bits() {
+        assert_eq!(FrameFormat::Rgba8.to_rgba16(&[0, 128, 255, 255]), [0, 32896, 65535, 65535]);
+        let le: Vec<u8> = [1_u16, 2, 0xabcd, 65535].iter().flat_map(|v| v.to_le_bytes()).collect();
+        assert_eq!(FrameFormat::Rgba16.to_rgba16(&le), [1, 2, 0xabcd, 65535]);
+        // Float colour is sRGB-encoded and clamped; alpha stays linear
+        let float = f32_bytes(&[0.0, 1.0, 4.0, 0.5]);
+        assert_eq!(FrameFormat::Rgba32f.to_rgba16(&float), [0, 65535, 65535, 32768]);
+    }
+
+    #[test]
+    fn linearises_integer_colour_but_not_alpha() {
+        let linear = FrameFormat::Rgba8.to_linear_f32(&[0, 188, 255, 128]);
+        assert_eq!(linear[0], 0.0);
+        assert!((linear[1] - 0.5).abs() < 0.01, "{}", linear[1]);
+        assert_eq!(linear[2], 1.0);
+        assert!((linear[3] - 128.0 / 255.0).abs() < 1e-6);
+        let float = [0.25, 2.0, -0.5, 1.0];
+        assert_eq!(FrameFormat::Rgba32f.to_linear_f32(&f32_bytes(&float)), float);
+    }
+
+    #[test]
+    fn writes_linear_pixels_back() {
+        for format in [FrameFormat::Rgba8, FrameFormat::Rgba16, FrameFormat::Rgba32f] {
+            let frame = match format {
+                FrameFormat::Rgba8   => vec![10, 128, 250, 200],
+                FrameFormat::Rgba16  => [10_u16, 32768, 65000, 40000].iter().flat_map(|v| v.to_le_bytes()).collect(),
+                FrameFormat::Rgba32f => f32_bytes(&[0.1, 3.5, 0.0, 0.75]),
+            };
+            let linear = format.to_linear_f32(&frame);
+            let mut out = vec![0; format.bytes_per_pixel()];
+            format.write_linear_pixel([linear[0], linear[1], linear[2], linear[3]], &mut out);
+            assert_eq!(out, frame, "{format:?}");
+        }
+    }
+
+    #[test]
+    fn linear_conversion_keeps_alpha_when_asked() {
+        assert!(HdrTransfer::Pq.linear_conversion_filter(false).starts_with("format=gbrpf32le,"));
+        assert!(HdrTransfer::Hlg.linear_conversion_filter(true).starts_with("format=gbrapf32le,"));
+        assert!(HdrTransfer::Hlg.linear_conversion_filter(true).contains("transfer=arib-std-b67"));
+    }
+}
@@ -0,0 +1,107 @@
+//! Animated GIF output.
+//!
+//! Uses FFmpeg's palettegen/paletteuse in a single filter graph: the stream is
+//! split, one branch builds an optimised palette from every frame, the other
+//! is mapped onto it once the palette is ready.
+
+use serde::Deserialize;
+
+const DITHER_MODES: &[&str] = &[
+    "none", "bayer", "heckbert", "floyd_steinberg", "sierra2", "sierra2_4a", "sierra3", "burkes",
+];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GifOptions {
+    /// One of `DITHER_MODES`.
+    #[serde(default = "default_dither")]
+    pub dither: String,
+    /// 0 loops forever, -1 plays once, N repeats N times.
+    #[serde(default)]
+    pub loop_count: i32,
+    /// Output frame rate; frames are dropped to hit it. Defaults to the input rate.
+    pub fps: Option<u32>,
+    /// Palette size, 2–256.
+    #[serde(default = "default_max_colors")]
+    pub max_colors: u32,
+}
+
+fn default_dither() -> String { "sierra2_4a".into() }
+fn default_max_colors() -> u32 { 256 }
+
+impl Default for GifOptions {
+    fn default() -> Self {
+        Self { dither: default_dither(), loop_count: 0, fps: None, max_colors: default_max_colors() }
+    }
+}
+
+impl GifOptions {
+    /// Output args replacing the usual video codec args. `prefilter` (a `-vf`
+    /// style chain, possibly empty) runs before the palette is built.
+    pub fn output_args(&self, prefilter: &str) -> Result<Vec<String>, String> {
+        if !DITHER_MODES.contains(&self.dither.as_str()) {
+            return Err(format!(
+                "Unknown GIF dither mode {:?} (expected one of {})",
+                self.dither, DITHER_MODES.join(", ")
+            ));
+        }
+        if !(2..=256).contains(&self.max_colors) {
+            return Err(format!("GIF max colours must be 2–256, got {}", self.max_colors));
+        }
+        if self.loop_count < -1 {
+            return Err(format!("GIF loop count must be -1 or more, got {}", self.loop_count));
+        }
+
+        let pre = if prefilter.is_empty() { String::new() } else { format!("{prefilter},") };
+        let fps = self.fps.map(|f| format!("fps={f},")).unwrap_or_default();
+        let graph = format!(
+            "[0:v]{pre}{fps}split[a][b];\
+             [a]palettegen=max_colors={}:stats_mode=diff[p];\
+             [b][p]paletteuse=dither={}:diff_mode=rectangle",
+            self.max_colors, self.dither,
+        );
+        Ok(vec![
+            "-filter_complex".into(), graph,
+            "-loop".into(), self.loop_count.to_string(),
+            "-f".into(), "gif".into(),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_one_palette_graph() {
+        let gif  = GifOptions { fps: Some(15), loop_count: -1, ..Default::default() };
+        let args = gif.output_args("vflip").unwrap();
+        assert_eq!(args, [
+            "-filter_complex",
+            "[0:v]vflip,fps=15,split[a][b];\
+             [a]palettegen=max_colors=256:stats_mode=diff[p];\
+             [b][p]paletteuse=dither=sierra2_4a:diff_mode=rectangle",
+            "-loop", "-1",
+            "-f", "gif",
+        ]);
+    }
+
+    #[test]
+    fn no_prefilter_starts_at_the_split() {
+        let args = GifOptions::default().output_args("").unwrap();
+        assert!(args[1].starts_with("[0:v]split[a][b];"), "{}", args[1]);
+    }
+
+    #[test]
+    fn rejects_bad_options() {
+        let cases = [
+            GifOptions { dither: "ordered".into(), ..Default::default() },
+            GifOptions { max_colors: 1, ..Default::default() },
+            GifOptions { max_colors: 257, ..Default::default() },
+            GifOptions { loop_count: -2, ..Default::default() },
+        ];
+        for gif in cases {
+            assert!(gif.output_args("").is_err(), "{gif:?}");
+        }
+    }
+}
@@ -1,131 +1,1165 @@
+mod accumulate;
+mod atomic;
+mod audio;
+mod cache;
+mod codec;
+mod config;
+mod edit;
+mod encode;
+mod error;
+mod filters;
+mod frame;
+mod gif;
+mod locate;
+mod ordering;
+mod preflight;
+mod presets;
+mod probe;
+mod progress;
+mod queue;
+mod sequence;
+mod settings;
+mod status;
+mod tiles;
+mod writer;
+
+use std::collections::HashMap;
 use std::io::Write;
-use std::process::{Child, ChildStdin, Command, Stdio};
-use std::sync::Mutex;
-use tauri::State;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use serde::Serialize;
+use tauri::ipc::InvokeBody;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use atomic::PendingOutputs;
+use cache::{CacheEntry, CacheKey, CacheReader, CacheRecorder, ReencodeFinished};
+use codec::EncoderSettings;
+use edit::{ConcatOptions, EditJobId, EditState, TranscodeOptions, TrimOptions};
+use encode::EncodeOptions;
+use error::EncodeError;
+use frame::FrameFormat;
+use locate::FfmpegStatus;
+use ordering::{FrameMap, FrameOrder, GapPolicy};
+use preflight::Preflight;
+use probe::{FfmpegProbe, ProbeCache};
+use queue::{JobId, QueueSnapshot, RenderJob, RenderJobSpec, RenderQueue};
+use progress::{FfmpegLog, LOG_TAIL_LINES};
+use sequence::{ImageSequence, SequenceOptions};
+use status::{EncodeStatus, FrameClock};
+use tiles::{TileProgress, TileState, TiledStill, TiledStillOptions};
+use writer::{FrameWriter, WriteStats};
 
 // ── FFmpeg session state ──────────────────────────────────────────────────────
 
-struct FfmpegSession {
+struct FfmpegProcess {
     child: Child,
-    stdin: ChildStdin,
+    /// Sibling temp file FFmpeg writes; renamed onto `output_path` on success.
+    temp_path: PathBuf,
+    log: FfmpegLog,
+    readers: Vec<JoinHandle<()>>,
+}
+
+/// Where a session's frames end up.
+enum SessionOutput {
+    /// Piped into an FFmpeg process writing a temp file for `output_path`.
+    Ffmpeg(FfmpegProcess),
+    /// Encoded in Rust to numbered image files.
+    Images(ImageSequence),
+}
+
+struct FfmpegSession {
+    writer: FrameWriter,
+    output: SessionOutput,
     width: u32,
     height: u32,
+    fps: u32,
+    codec: String,
+    output_path: String,
+    frames: u64,
+    input_format: FrameFormat,
+    /// Output frames the frontend expects to send, for the ETA.
+    expected_frames: Option<u64>,
+    /// Sub-frames per output frame (motion blur samples, or 1).
+    samples_per_frame: u32,
+    clock: FrameClock,
+    /// Puts indexed frames back in order. Locked separately from the session
+    /// map so a blocked send only holds up its own session.
+    order: Arc<Mutex<FrameOrder>>,
+    /// Records the frames into the frame cache, if requested.
+    cache: Option<CacheRecorder>,
 }
 
-struct FfmpegState(Mutex<Option<FfmpegSession>>);
+impl FfmpegSession {
+    /// A write failure, with FFmpeg's log tail added for FFmpeg-backed sessions.
+    fn with_log_tail(&self, e: EncodeError) -> EncodeError {
+        match &self.output {
+            SessionOutput::Ffmpeg(process) => with_log_tail(e, &process.log),
+            SessionOutput::Images(_)       => e,
+        }
+    }
 
-// ── Tauri commands ────────────────────────────────────────────────────────────
+    /// Queue frames still held for reordering, filling any gaps up to the
+    /// expected length, and return the session's frame map.
+    fn flush_frames(&self) -> Result<FrameMap, EncodeError> {
+        let mut order = self.order.lock()?;
+        let sender    = self.writer.sender();
+        for frame in order.finish()? {
+            sender.send(frame).map_err(|e| self.with_log_tail(e))?;
+        }
+        Ok(order.map())
+    }
 
-/// Start an FFmpeg encoding session.
-/// `codec` is one of: "h264", "prores", "ffv1"
-/// Returns an error string if FFmpeg can't be found or the session is already active.
-#[tauri::command]
-fn start_ffmpeg_encode(
-    state: State<FfmpegState>,
+    /// The temp file an FFmpeg-backed session is writing.
+    fn temp_path(&self) -> Option<PathBuf> {
+        match &self.output {
+            SessionOutput::Ffmpeg(process) => Some(process.temp_path.clone()),
+            SessionOutput::Images(_)       => None,
+        }
+    }
+
+    /// Write out queued frames, then close FFmpeg stdin and wait for the process
+    /// to finish encoding. Only a successful encode is moved onto `output_path`;
+    /// otherwise the temp file is removed and any existing output left alone.
+    /// A session stopped by the free-space watchdog is the exception: FFmpeg
+    /// still finalises what was written, which is kept — at `output_path` if
+    /// that was free, otherwise beside it (see `atomic::keep_truncated`) — and
+    /// the `InsufficientSpace` error is returned naming where.
+    fn finish(self) -> Result<(), EncodeError> {
+        // Finishing the writer drains the queue and closes stdin — FFmpeg will then
+        // flush and exit cleanly
+        let drained = self.writer.finish();
+        let SessionOutput::Ffmpeg(FfmpegProcess { mut child, temp_path, log, readers }) = self.output else {
+            return drained;
+        };
+        let status = child.wait().map_err(|e| {
+            let _ = std::fs::remove_file(&temp_path);
+            EncodeError::Io { message: format!("FFmpeg wait error: {e}") }
+        })?;
+
+        // The readers finish once FFmpeg closes its end of the pipes
+        for reader in readers {
+            let _ = reader.join();
+        }
+
+        let result = if !status.success() {
+            Err(EncodeError::NonZeroExit { code: status.code(), log_tail: log.tail(LOG_TAIL_LINES) })
+        } else {
+            drained.map_err(|e| with_log_tail(e, &log))
+        };
+        match result {
+            Ok(()) => atomic::commit(&temp_path, Path::new(&self.output_path)),
+            Err(EncodeError::InsufficientSpace { path, required_bytes, free_bytes, .. }) => {
+                let kept = atomic::keep_truncated(&temp_path, Path::new(&self.output_path))?;
+                Err(EncodeError::InsufficientSpace {
+                    path,
+                    required_bytes,
+                    free_bytes,
+                    kept_path: Some(kept.display().to_string()),
+                })
+            }
+            Err(e) => {
+                let _ = std::fs::remove_file(&temp_path);
+                Err(e)
+            }
+        }
+    }
+
+    /// Stop without finalising and delete the partial output. An existing file at
+    /// `output_path` is never touched.
+    fn abort(self) {
+        if let Some(cache) = self.cache {
+            cache.discard();
+        }
+        match self.output {
+            SessionOutput::Ffmpeg(FfmpegProcess { mut child, temp_path, readers, .. }) => {
+                // Killing first breaks the pipe, so the writer stops instead of draining
+                let _ = child.kill();
+                let _ = self.writer.finish();
+                let _ = child.wait();
+                for reader in readers {
+                    let _ = reader.join();
+                }
+                let _ = std::fs::remove_file(&temp_path);
+            }
+            SessionOutput::Images(sequence) => {
+                // Count what the sink accepted once it has stopped, not what was
+                // queued; with motion blur every `samples_per_frame` make one file
+                let stats   = self.writer.stats();
+                let _       = self.writer.finish();
+                let written = stats.frames.load(Ordering::Relaxed) / self.samples_per_frame.max(1) as u64;
+                sequence.remove_frames(written);
+            }
+        }
+    }
+}
+
+/// Fill in FFmpeg's log tail on a pipe error; other errors pass through.
+fn with_log_tail(e: EncodeError, log: &FfmpegLog) -> EncodeError {
+    match e {
+        EncodeError::PipeBroken { message, .. } => {
+            EncodeError::PipeBroken { message, log_tail: log.tail(LOG_TAIL_LINES) }
+        }
+        e => e,
+    }
+}
+
+/// Handle returned by `start_ffmpeg_encode`; passed back to address a session.
+type SessionId = u32;
+
+#[derive(Default)]
+struct FfmpegState {
+    sessions: Mutex<HashMap<SessionId, FfmpegSession>>,
+    next_id: AtomicU32,
+}
+
+impl FfmpegState {
+    /// Register a started session — unless another session has claimed the same
+    /// output since `ensure_output_free` checked it, in which case the new one
+    /// is aborted and `SessionBusy` returned.
+    fn insert(&self, id: SessionId, session: FfmpegSession) -> Result<(), EncodeError> {
+        let mut sessions = self.sessions.lock()?;
+        if sessions.values().any(|s| s.output_path == session.output_path) {
+            drop(sessions);
+            let output_path = session.output_path.clone();
+            session.abort();
+            return Err(EncodeError::SessionBusy { output_path });
+        }
+        sessions.insert(id, session);
+        Ok(())
+    }
+
+    /// Abort every live session — used when the window closes mid-encode so no
+    /// FFmpeg process outlives the app.
+    fn abort_all(&self) {
+        let sessions: Vec<FfmpegSession> = match self.sessions.lock() {
+            Ok(mut s)  => s.drain().map(|(_, s)| s).collect(),
+            Err(poison) => poison.into_inner().drain().map(|(_, s)| s).collect(),
+        };
+        for session in sessions {
+            session.abort();
+        }
+    }
+}
+
+impl Drop for FfmpegState {
+    fn drop(&mut self) {
+        self.abort_all();
+    }
+}
+
+/// Snapshot of a live session, as reported by `list_encode_sessions`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EncodeSessionInfo {
+    session_id: SessionId,
     output_path: String,
     width: u32,
     height: u32,
     fps: u32,
     codec: String,
-) -> Result<(), String> {
-    let mut guard = state.0.lock().map_err(|e| e.to_string())?;
-    if guard.is_some() {
-        return Err("FFmpeg session already active".into());
-    }
-
-    // Resolve FFmpeg binary — ffmpeg-sidecar will download a static build on first use
-    ffmpeg_sidecar::download::auto_download().map_err(|e| e.to_string())?;
-    let ffmpeg_path = ffmpeg_sidecar::paths::ffmpeg_path();
-
-    // Build codec-specific output args
-    let codec_args: Vec<&str> = match codec.as_str() {
-        "prores" => vec![
-            "-c:v", "prores_ks",
-            "-profile:v", "3",         // ProRes 422 HQ
-            "-vendor", "apl0",
-            "-pix_fmt", "yuv422p10le",
-        ],
-        "ffv1" => vec![
-            "-c:v", "ffv1",
-            "-level", "3",
-            "-coder", "1",
-            "-context", "1",
-            "-pix_fmt", "yuv420p",
-        ],
-        _ => vec![              // h264 (default)
-            "-c:v", "libx264",
-            "-preset", "slow",
-            "-crf", "18",
-            "-pix_fmt", "yuv420p",
-        ],
-    };
+    frames: u64,
+}
+
+/// Returned by `send_frame_rgba` so the render loop can see encoder backpressure.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FrameAck {
+    /// Frames waiting to be written, including the one just sent.
+    queue_depth: usize,
+    queue_capacity: usize,
+}
+
+/// Resolve the FFmpeg binary (see `locate`), downloading a static build as a last resort.
+fn resolve_ffmpeg(app: &AppHandle) -> Result<PathBuf, EncodeError> {
+    locate::resolve(app, true)?
+        .map(|r| r.path)
+        .ok_or(EncodeError::FfmpegNotFound { path: None })
+}
+
+// ── Session lifecycle ─────────────────────────────────────────────────────────
+//
+// Shared by the commands below and the render queue.
 
-    let fps_str  = fps.to_string();
-    let size_str = format!("{}x{}", width, height);
+/// Fail with `SessionBusy` if an encode session, tiled still or edit job is
+/// writing `output_path`. Every command that starts writing a file checks this
+/// first; `FfmpegState::insert` and `edit::spawn` check their own kind again
+/// while registering.
+fn ensure_output_free(app: &AppHandle, output_path: &str) -> Result<(), EncodeError> {
+    let encoding = app.state::<FfmpegState>().sessions.lock()?
+        .values()
+        .any(|s| s.output_path == output_path);
+    let tiling = app.state::<TileState>().sessions.lock()?
+        .values()
+        .any(|s| s.output_path() == output_path);
+    if encoding || tiling || app.state::<EditState>().is_writing(output_path)? {
+        return Err(EncodeError::SessionBusy { output_path: output_path.to_string() });
+    }
+    Ok(())
+}
+
+/// Spawn FFmpeg for `options` and register the session; see `start_ffmpeg_encode`.
+fn start_encode(
+    app: &AppHandle,
+    state: &FfmpegState,
+    pending: &PendingOutputs,
+    mut options: EncodeOptions,
+) -> Result<SessionId, EncodeError> {
+    // Check for conflicts early — the lock isn't held while FFmpeg is resolved
+    // and spawned, so other sessions keep receiving frames in the meantime.
+    // `FfmpegState::insert` checks the other sessions again.
+    ensure_output_free(app, &options.output_path)?;
+
+    if let Some(name) = &options.preset {
+        options.encoder = presets::get(app, name)?;
+    }
+    if let Some(blur) = &options.motion_blur {
+        blur.validate()?;
+    }
+    let id        = state.next_id.fetch_add(1, Ordering::Relaxed);
+    let temp_path = atomic::temp_path(Path::new(&options.output_path), id);
+    let args      = options.ffmpeg_args_to(&temp_path.to_string_lossy())?;
+    preflight::check(&options)?;
+
+    let ffmpeg_path = resolve_ffmpeg(app)?;
+    let cache = match options.cache.clone() {
+        Some(key) => {
+            if (key.width, key.height, key.fps) != (options.width, options.height, options.fps) {
+                return Err("Frame cache key doesn't match the encode's size and fps".into());
+            }
+            let limit = settings::load(app)?.frame_cache_limit.unwrap_or(cache::DEFAULT_LIMIT);
+            Some(CacheRecorder::create(&cache::root(app)?, key, options.input_format, limit, id)?)
+        }
+        None => None,
+    };
 
     let mut cmd = Command::new(&ffmpeg_path);
-    cmd.args([
-        "-y",                      // overwrite
-        "-f", "rawvideo",
-        "-vcodec", "rawvideo",
-        "-pix_fmt", "rgba",
-        "-s", &size_str,
-        "-r", &fps_str,
-        "-i", "pipe:0",            // read frames from stdin
-    ]);
-    cmd.args(&codec_args);
-    cmd.args(["-movflags", "+faststart"]);
-    cmd.arg(&output_path);
+    cmd.args(&args);
     cmd.stdin(Stdio::piped())
-        .stdout(Stdio::null())
-        .stderr(Stdio::null());
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    pending.add(&temp_path);
+    let mut child = cmd.spawn().map_err(|e| {
+        pending.remove(&temp_path);
+        EncodeError::Io { message: format!("Failed to spawn FFmpeg: {e}") }
+    })?;
+    let pipes = (child.stdin.take(), child.stdout.take(), child.stderr.take());
+    let (Some(mut stdin), Some(stdout), Some(stderr)) = pipes else {
+        let _ = child.kill();
+        let _ = child.wait();
+        let _ = std::fs::remove_file(&temp_path);
+        pending.remove(&temp_path);
+        return Err(EncodeError::Io { message: "Failed to get FFmpeg's stdin, stdout or stderr".into() });
+    };
 
-    let mut child = cmd.spawn().map_err(|e| format!("Failed to spawn FFmpeg: {e}"))?;
-    let stdin     = child.stdin.take().ok_or("Failed to get FFmpeg stdin")?;
+    let log = FfmpegLog::default();
+    let readers = vec![
+        progress::spawn_log_reader(stderr, log.clone()),
+        progress::spawn_progress_reader(stdout, app.clone(), id, log.clone()),
+    ];
+    let stats = Arc::new(WriteStats::default());
+    let mut sink = stats.count_bytes(Box::new(move |frame: &[u8]| {
+        stdin.write_all(frame).map_err(|e| EncodeError::PipeBroken {
+            message: format!("FFmpeg stdin write error: {e}"),
+            log_tail: String::new(),
+        })
+    }));
+    sink = preflight::watch_free_space(sink, preflight::watch_dir(&options.output_path));
+    if let Some(cache) = &cache {
+        sink = cache.tee(sink);
+    }
+    if let Some(blur) = options.motion_blur {
+        sink = blur.wrap(sink, options.input_format);
+    }
+    if options.gap_policy == GapPolicy::Repeat {
+        sink = ordering::repeat_previous(sink);
+    }
+    let samples_per_frame = options.motion_blur.map_or(1, |b| b.samples.max(1));
+    let total_samples     = options.expected_frames.map(|n| n * samples_per_frame as u64);
+    let session = FfmpegSession {
+        writer: FrameWriter::spawn(sink, stats),
+        output: SessionOutput::Ffmpeg(FfmpegProcess { child, temp_path: temp_path.clone(), log, readers }),
+        width: options.width,
+        height: options.height,
+        fps: options.fps,
+        codec: options.encoder.codec,
+        output_path: options.output_path,
+        frames: 0,
+        input_format: options.input_format,
+        expected_frames: options.expected_frames,
+        samples_per_frame,
+        clock: FrameClock::start(),
+        order: Arc::new(Mutex::new(FrameOrder::new(options.gap_policy, total_samples))),
+        cache,
+    };
+    if let Err(e) = state.insert(id, session) {
+        pending.remove(&temp_path);
+        return Err(e);
+    }
+    Ok(id)
+}
+
+/// Finalise a session; see `stop_ffmpeg_encode`.
+fn stop_encode(
+    state: &FfmpegState,
+    pending: &PendingOutputs,
+    session_id: SessionId,
+) -> Result<FrameMap, EncodeError> {
+    // Remove the session first so the lock isn't held while FFmpeg finalises
+    let mut session = state.sessions
+        .lock()?
+        .remove(&session_id)
+        .ok_or(EncodeError::NoSession { session_id })?;
+
+    let temp_path = session.temp_path();
+    let result = match session.flush_frames() {
+        Ok(map) => {
+            // Only a successful encode leaves a complete cache entry
+            let cache      = session.cache.take();
+            let mut result = session.finish().map(|()| map);
+            if let Some(cache) = cache {
+                match &mut result {
+                    Ok(map) => map.cache_warning = cache.commit(),
+                    Err(_)  => cache.discard(),
+                }
+            }
+            result
+        }
+        // Out of disk: keep what was written rather than throwing it away
+        Err(e @ EncodeError::InsufficientSpace { .. }) => {
+            if let Some(cache) = session.cache.take() {
+                cache.discard();
+            }
+            session.finish().and(Err(e))
+        }
+        Err(e)  => {
+            session.abort();
+            Err(e)
+        }
+    };
+    if let Some(path) = temp_path {
+        pending.remove(&path);
+    }
+    result
+}
 
-    *guard = Some(FfmpegSession { child, stdin, width, height });
+fn abort_encode(
+    state: &FfmpegState,
+    pending: &PendingOutputs,
+    session_id: SessionId,
+) -> Result<(), EncodeError> {
+    let session = state.sessions
+        .lock()?
+        .remove(&session_id)
+        .ok_or(EncodeError::NoSession { session_id })?;
+    let temp_path = session.temp_path();
+    session.abort();
+    if let Some(path) = temp_path {
+        pending.remove(&path);
+    }
     Ok(())
 }
 
-/// Send a single raw RGBA frame (width × height × 4 bytes) to FFmpeg stdin.
+// ── Tauri commands ────────────────────────────────────────────────────────────
+
+/// Start an FFmpeg encoding session described by `options` (see `EncodeOptions`).
+/// `options.encoder.codec` is one of: "h264", "prores", "ffv1", "vp9", "av1",
+/// "qtrle", "png", "gif"; anything else is rejected. `options.preset` names a
+/// saved encoder preset to use instead of `options.encoder`.
+/// Progress is reported through `ffmpeg-progress` events while the session runs.
+/// Returns the new session's id, or an error if FFmpeg can't be found, another
+/// session is already writing to `output_path`, or the preflight checks fail.
+/// While encoding, the session stops before free space drops below the reserve.
+/// FFmpeg writes to a temp file that `stop_ffmpeg_encode` renames onto
+/// `output_path` once the encode succeeds.
+#[tauri::command]
+fn start_ffmpeg_encode(
+    app: AppHandle,
+    state: State<FfmpegState>,
+    pending: State<PendingOutputs>,
+    options: EncodeOptions,
+) -> Result<SessionId, EncodeError> {
+    start_encode(&app, &state, &pending, options)
+}
+
+/// Start an image-sequence session: each frame sent with `send_frame_rgba` is
+/// written to `options.output_dir` as a numbered file. `options.pattern` names
+/// the files, e.g. `shot_%04d` (the extension comes from `options.format`).
+/// Doesn't need FFmpeg.
+#[tauri::command]
+fn start_image_sequence(
+    app: AppHandle,
+    state: State<FfmpegState>,
+    options: SequenceOptions,
+) -> Result<SessionId, EncodeError> {
+    let sequence = ImageSequence::new(&options)?;
+    let stats    = Arc::new(WriteStats::default());
+    let mut sink = stats.count_bytes(sequence.sink());
+    sink = preflight::watch_free_space(sink, options.output_dir.clone().into());
+    if let Some(blur) = options.motion_blur {
+        blur.validate()?;
+        sink = blur.wrap(sink, options.input_format);
+    }
+    if options.gap_policy == GapPolicy::Repeat {
+        sink = ordering::repeat_previous(sink);
+    }
+    let output_path = sequence.path_for(0).to_string_lossy().into_owned();
+    ensure_output_free(&app, &output_path)?;
+
+    let id = state.next_id.fetch_add(1, Ordering::Relaxed);
+    let samples_per_frame = options.motion_blur.map_or(1, |b| b.samples.max(1));
+    let total_samples     = options.expected_frames.map(|n| n * samples_per_frame as u64);
+    state.insert(id, FfmpegSession {
+        writer: FrameWriter::spawn(sink, stats),
+        output: SessionOutput::Images(sequence),
+        width: options.width,
+        height: options.height,
+        fps: options.fps,
+        codec: options.format.extension().to_string(),
+        output_path,
+        frames: 0,
+        input_format: options.input_format,
+        expected_frames: options.expected_frames,
+        samples_per_frame,
+        clock: FrameClock::start(),
+        order: Arc::new(Mutex::new(FrameOrder::new(options.gap_policy, total_samples))),
+        cache: None,
+    })?;
+    Ok(id)
+}
+
+/// Header carrying the target session id alongside a raw frame body.
+const SESSION_ID_HEADER: &str = "x-session-id";
+
+fn session_id_header(request: &tauri::ipc::Request<'_>) -> Result<SessionId, EncodeError> {
+    request
+        .headers()
+        .get(SESSION_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| "Missing or invalid x-session-id header".into())
+}
+
+/// The optional `x-frame-index` header; None if absent.
+fn frame_index_header(request: &tauri::ipc::Request<'_>) -> Result<Option<u64>, EncodeError> {
+    request
+        .headers()
+        .get(ordering::FRAME_INDEX_HEADER)
+        .map(|v| v.to_str().ok().and_then(|v| v.parse().ok()))
+        .map(|index| index.ok_or_else(|| "Invalid x-frame-index header".into()))
+        .transpose()
+}
+
+/// The frame bytes of a `send_frame_rgba` request, which must be a raw body.
+fn raw_frame(body: &InvokeBody) -> Result<&[u8], EncodeError> {
+    match body {
+        InvokeBody::Raw(data) => Ok(data),
+        InvokeBody::Json(_)   => Err("Expected a raw binary frame body".into()),
+    }
+}
+
+/// Queue a single raw RGBA frame for a session — width × height × 4 bytes for
+/// `rgba8` input, ×8 for `rgba16` and ×16 for `rgba32f`.
+///
+/// The frame is the raw IPC request body (`invoke('send_frame_rgba', bytes, { headers })`)
+/// rather than a JSON argument, so it crosses the bridge without being serialised as an
+/// array of numbers. The session id travels in the `x-session-id` header, and the
+/// frame's 0-based index in `x-frame-index` — frames are written in index order
+/// whatever order they arrive in (see `ordering`). Without an index, frames are
+/// numbered in arrival order.
+///
+/// Returns once the frame is queued (or held until earlier frames arrive); a
+/// background thread does the actual write. If the queue is full this blocks
+/// until FFmpeg catches up.
+/// A gap that can't be filled (see `ordering`) aborts the session.
 #[tauri::command]
 fn send_frame_rgba(
     state: State<FfmpegState>,
-    data: Vec<u8>,
-) -> Result<(), String> {
-    let mut guard = state.0.lock().map_err(|e| e.to_string())?;
-    let session   = guard.as_mut().ok_or("No active FFmpeg session")?;
+    pending: State<PendingOutputs>,
+    request: tauri::ipc::Request<'_>,
+) -> Result<FrameAck, EncodeError> {
+    let data        = raw_frame(request.body())?;
+    let session_id  = session_id_header(&request)?;
+    let frame_index = frame_index_header(&request)?;
+
+    // Validate and grab a buffer under the lock; copy and queue outside it
+    let (sender, order, index, mut buf) = {
+        let mut sessions = state.sessions.lock()?;
+        let session      = sessions.get_mut(&session_id).ok_or(EncodeError::NoSession { session_id })?;
+
+        let expected = session.input_format.frame_len(session.width, session.height);
+        if data.len() != expected {
+            return Err(EncodeError::FrameSizeMismatch { got: data.len(), expected });
+        }
+        if let Some(e) = session.writer.error() {
+            return Err(session.with_log_tail(e));
+        }
+
+        let index = frame_index.unwrap_or(session.frames);
+        session.frames += 1;
+        (session.writer.sender(), session.order.clone(), index, session.writer.buffer())
+    };
+
+    buf.clear();
+    buf.extend_from_slice(data);
+
+    // Holding the order lock while queueing keeps this session's sends in order
+    let mut guard = order.lock()?;
+    let ready = match guard.accept(index, buf) {
+        Ok(ready) => ready,
+        // A gap that can't be filled ends the session
+        Err(e @ EncodeError::MissingFrame { .. }) => {
+            drop((guard, sender));
+            let _ = abort_encode(&state, &pending, session_id);
+            return Err(e);
+        }
+        Err(e) => return Err(e),
+    };
+    let mut queue_depth = sender.depth();
+    for frame in ready {
+        queue_depth = sender.send(frame).map_err(|e| {
+            // Quote the log if the session is still around to read it from
+            state.sessions.lock().ok()
+                .and_then(|s| s.get(&session_id).map(|s| s.with_log_tail(e.clone())))
+                .unwrap_or(e)
+        })?;
+    }
+
+    Ok(FrameAck { queue_depth, queue_capacity: writer::FRAME_QUEUE_CAPACITY })
+}
+
+/// Write out a session's queued frames, close FFmpeg stdin and wait for the
+/// process to finish encoding, then move the result onto `output_path`.
+/// Frames still held for reordering are written first, with gaps handled by
+/// the session's gap policy; a gap under `fail` aborts the session instead.
+/// A session stopped for lack of disk space is still finalised and its
+/// truncated output kept without replacing an existing file; the returned
+/// `InsufficientSpace` error says where.
+/// Returns which frames were repeated, received twice or arrived too late.
+#[tauri::command]
+fn stop_ffmpeg_encode(
+    state: State<FfmpegState>,
+    pending: State<PendingOutputs>,
+    session_id: SessionId,
+) -> Result<FrameMap, EncodeError> {
+    stop_encode(&state, &pending, session_id)
+}
+
+/// Kill a session's FFmpeg process and remove its partially written output.
+#[tauri::command]
+fn abort_ffmpeg_encode(
+    state: State<FfmpegState>,
+    pending: State<PendingOutputs>,
+    session_id: SessionId,
+) -> Result<(), EncodeError> {
+    abort_encode(&state, &pending, session_id)
+}
+
+/// List every live encoding session, ordered by session id.
+#[tauri::command]
+fn list_encode_sessions(state: State<FfmpegState>) -> Result<Vec<EncodeSessionInfo>, EncodeError> {
+    let sessions = state.sessions.lock()?;
+    let mut list: Vec<EncodeSessionInfo> = sessions
+        .iter()
+        .map(|(&session_id, s)| EncodeSessionInfo {
+            session_id,
+            output_path: s.output_path.clone(),
+            width: s.width,
+            height: s.height,
+            fps: s.fps,
+            codec: s.codec.clone(),
+            frames: s.frames,
+        })
+        .collect();
+    list.sort_by_key(|s| s.session_id);
+    Ok(list)
+}
+
+/// Check that `options.output_path` is writable and the estimated output fits
+/// in the free space, without spawning anything. `start_ffmpeg_encode` runs the
+/// same checks but drops the warnings, so call this first to show them.
+#[tauri::command]
+fn preflight_ffmpeg_encode(app: AppHandle, mut options: EncodeOptions) -> Result<Preflight, EncodeError> {
+    if let Some(name) = &options.preset {
+        options.encoder = presets::get(&app, name)?;
+    }
+    options.ffmpeg_args()?;
+    preflight::check(&options)
+}
+
+/// Return the FFmpeg command line `start_ffmpeg_encode` would run for `options`,
+/// without spawning anything. Useful for checking what a preset produces. The
+/// preview names `output_path` itself; a real session writes a temp file first.
+#[tauri::command]
+fn preview_ffmpeg_command(app: AppHandle, mut options: EncodeOptions) -> Result<String, EncodeError> {
+    if let Some(name) = &options.preset {
+        options.encoder = presets::get(&app, name)?;
+    }
+    let args = options.ffmpeg_args()?;
+    // Don't download just to print a command line
+    let ffmpeg_path = locate::resolve(&app, false)?
+        .map(|r| r.path)
+        .unwrap_or_else(|| PathBuf::from("ffmpeg"));
+    Ok(encode::command_line(&ffmpeg_path, &args))
+}
+
+/// All saved encoder presets, keyed by name.
+#[tauri::command]
+fn list_encoder_presets(app: AppHandle) -> Result<presets::Presets, EncodeError> {
+    presets::load(&app)
+}
+
+/// Save (or overwrite) a named encoder preset after checking it produces valid args.
+#[tauri::command]
+fn save_encoder_preset(app: AppHandle, name: String, settings: EncoderSettings) -> Result<(), EncodeError> {
+    if name.trim().is_empty() {
+        return Err("Preset name must not be empty".into());
+    }
+    if settings.codec != "gif" {
+        settings.video_args(false)?;
+    }
+    let mut all = presets::load(&app)?;
+    all.insert(name, settings);
+    presets::save(&app, &all)
+}
+
+#[tauri::command]
+fn delete_encoder_preset(app: AppHandle, name: String) -> Result<(), EncodeError> {
+    let mut all = presets::load(&app)?;
+    all.remove(&name).ok_or_else(|| format!("No encoder preset named {name:?}"))?;
+    presets::save(&app, &all)
+}
+
+/// Report the FFmpeg version, its encoders, pixel formats and muxers, and which
+/// codec options `start_ffmpeg_encode` can use with it. The result is cached;
+/// pass `refresh` to probe again.
+#[tauri::command]
+fn probe_ffmpeg(
+    app: AppHandle,
+    cache: State<ProbeCache>,
+    refresh: Option<bool>,
+) -> Result<FfmpegProbe, EncodeError> {
+    let ffmpeg_path = resolve_ffmpeg(&app)?;
+    let mut cached = cache.0.lock()?;
+    if let Some(probe) = cached.as_ref() {
+        if !refresh.unwrap_or(false) && probe.ffmpeg_path == ffmpeg_path {
+            return Ok(probe.clone());
+        }
+    }
+    let probe = probe::probe(&ffmpeg_path)?;
+    *cached = Some(probe.clone());
+    Ok(probe)
+}
+
+/// Report which FFmpeg binary would be used and where it came from, without
+/// downloading anything.
+#[tauri::command]
+fn get_ffmpeg_status(app: AppHandle) -> Result<FfmpegStatus, EncodeError> {
+    let resolved = locate::resolve(&app, false)?;
+    let version  = resolved.as_ref().and_then(|r| locate::validate(&r.path).ok());
+    Ok(FfmpegStatus { resolved, version })
+}
+
+/// Save the FFmpeg binary to use from now on, after checking it runs and
+/// really is FFmpeg. Pass `None` to go back to automatic resolution.
+/// Returns the binary's version.
+#[tauri::command]
+fn set_ffmpeg_path(
+    app: AppHandle,
+    cache: State<ProbeCache>,
+    path: Option<String>,
+) -> Result<Option<String>, EncodeError> {
+    let path    = path.map(PathBuf::from);
+    let version = path.as_deref().map(locate::validate).transpose()?;
+
+    let mut settings = settings::load(&app)?;
+    settings.ffmpeg_path = path;
+    settings::save(&app, &settings)?;
+
+    // The cached probe belongs to whichever binary was used before
+    *cache.0.lock()? = None;
+    Ok(version)
+}
+
+/// Start a tiled still render of `options.width`×`options.height`, split into
+/// tiles of `options.tile_width`×`options.tile_height`. Returns the session id
+/// to send tiles to.
+#[tauri::command]
+fn start_tiled_still(
+    app: AppHandle,
+    state: State<TileState>,
+    pending: State<PendingOutputs>,
+    options: TiledStillOptions,
+) -> Result<SessionId, EncodeError> {
+    ensure_output_free(&app, &options.output_path)?;
+    let id    = state.next_id.fetch_add(1, Ordering::Relaxed);
+    let still = TiledStill::start(options, id)?;
+    pending.add(still.temp_path());
+    state.sessions.lock()?.insert(id, still);
+    Ok(id)
+}
+
+/// Send one tile as a raw top-down RGBA body. Headers: `x-session-id`,
+/// `x-tile-x` and `x-tile-y` (tile column and row). Tiles must arrive row by
+/// row; a `tile-progress` event follows every accepted tile.
+#[tauri::command]
+fn send_tile(
+    app: AppHandle,
+    state: State<TileState>,
+    request: tauri::ipc::Request<'_>,
+) -> Result<TileProgress, EncodeError> {
+    let InvokeBody::Raw(data) = request.body() else {
+        return Err("Expected a raw binary tile body".into());
+    };
+    let session_id = session_id_header(&request)?;
+    let (x, y)     = tiles::tile_coords(request.headers())?;
+
+    let mut stills = state.sessions.lock()?;
+    let still      = stills.get_mut(&session_id).ok_or(EncodeError::NoSession { session_id })?;
+    let progress = TileProgress {
+        session_id,
+        tiles_done: still.add_tile(x, y, data)?,
+        tiles_total: still.tiles_total(),
+    };
+    let _ = app.emit(tiles::TILE_PROGRESS_EVENT, progress.clone());
+    Ok(progress)
+}
+
+/// Write the last tile row, close the image and move it onto `output_path`.
+/// Fails (and deletes the partial image) if tiles are missing.
+#[tauri::command]
+fn finish_tiled_still(
+    state: State<TileState>,
+    pending: State<PendingOutputs>,
+    session_id: SessionId,
+) -> Result<(), EncodeError> {
+    let still = state.sessions
+        .lock()?
+        .remove(&session_id)
+        .ok_or(EncodeError::NoSession { session_id })?;
+    let temp_path = still.temp_path().to_path_buf();
+    let result    = still.finish();
+    pending.remove(&temp_path);
+    result
+}
+
+#[tauri::command]
+fn abort_tiled_still(
+    state: State<TileState>,
+    pending: State<PendingOutputs>,
+    session_id: SessionId,
+) -> Result<(), EncodeError> {
+    let still = state.sessions
+        .lock()?
+        .remove(&session_id)
+        .ok_or(EncodeError::NoSession { session_id })?;
+    pending.remove(still.temp_path());
+    still.abort();
+    Ok(())
+}
+
+/// Frame counts, throughput and ETA for a live session.
+#[tauri::command]
+fn get_encode_status(state: State<FfmpegState>, session_id: SessionId) -> Result<EncodeStatus, EncodeError> {
+    let mut sessions = state.sessions.lock()?;
+    let session      = sessions.get_mut(&session_id).ok_or(EncodeError::NoSession { session_id })?;
+
+    let stats          = session.writer.stats();
+    let frames_written = stats.frames.load(Ordering::Relaxed);
+    let bytes_written  = stats.bytes.load(Ordering::Relaxed);
+    let (elapsed_secs, average_fps, current_fps) = session.clock.sample(frames_written);
+
+    let rate = if current_fps > 0.0 { current_fps } else { average_fps };
+    let eta_secs = session.expected_frames
+        .map(|n| n * session.samples_per_frame as u64)
+        .filter(|_| rate > 0.0)
+        .map(|total| total.saturating_sub(frames_written) as f64 / rate);
+
+    Ok(EncodeStatus {
+        session_id,
+        frames_received: session.frames,
+        frames_written,
+        bytes_written,
+        queue_depth: session.writer.depth(),
+        elapsed_secs,
+        average_fps,
+        current_fps,
+        expected_frames: session.expected_frames,
+        eta_secs,
+    })
+}
+
+// ── Render queue ──────────────────────────────────────────────────────────────
+
+/// Add a job to the end of the render queue.
+#[tauri::command]
+fn enqueue_render_job(
+    app: AppHandle,
+    queue: State<RenderQueue>,
+    spec: RenderJobSpec,
+) -> Result<RenderJob, EncodeError> {
+    let job = queue.enqueue(spec)?;
+    queue::emit_status(&app, &job);
+    Ok(job)
+}
+
+/// Every job in queue order, and whether the queue is paused.
+#[tauri::command]
+fn list_render_jobs(queue: State<RenderQueue>) -> Result<QueueSnapshot, EncodeError> {
+    queue.snapshot()
+}
+
+/// Stop handing out jobs; a running job carries on to the end.
+#[tauri::command]
+fn pause_render_queue(queue: State<RenderQueue>) -> Result<(), EncodeError> {
+    queue.set_paused(true)
+}
+
+#[tauri::command]
+fn resume_render_queue(queue: State<RenderQueue>) -> Result<(), EncodeError> {
+    queue.set_paused(false)
+}
+
+/// Move a job to `position` (0-based) in the queue.
+#[tauri::command]
+fn move_render_job(queue: State<RenderQueue>, job_id: JobId, position: usize) -> Result<(), EncodeError> {
+    queue.move_job(job_id, position)
+}
+
+/// Cancel a queued job, or abort a running one's encode.
+#[tauri::command]
+fn cancel_render_job(
+    app: AppHandle,
+    queue: State<RenderQueue>,
+    state: State<FfmpegState>,
+    pending: State<PendingOutputs>,
+    job_id: JobId,
+) -> Result<RenderJob, EncodeError> {
+    let job = queue.cancel(job_id)?;
+    if let Some(session_id) = job.session_id {
+        // The session may already be gone if the frontend aborted it first
+        let _ = abort_encode(&state, &pending, session_id);
+    }
+    queue::emit_status(&app, &job);
+    Ok(job)
+}
+
+/// Remove done, failed and cancelled jobs.
+#[tauri::command]
+fn clear_finished_render_jobs(queue: State<RenderQueue>) -> Result<(), EncodeError> {
+    queue.clear_finished()
+}
 
-    let expected = (session.width * session.height * 4) as usize;
-    if data.len() != expected {
-        return Err(format!(
-            "Frame size mismatch: got {} bytes, expected {}",
-            data.len(), expected
-        ));
+/// Start the next queued job's encode session and return the job with its
+/// `sessionId` set. Returns null when the queue is paused, empty, or already
+/// running a job. Jobs whose session fails to start are marked failed and
+/// skipped.
+#[tauri::command]
+fn claim_render_job(
+    app: AppHandle,
+    queue: State<RenderQueue>,
+    state: State<FfmpegState>,
+    pending: State<PendingOutputs>,
+) -> Result<Option<RenderJob>, EncodeError> {
+    while let Some(mut job) = queue.claim_next()? {
+        match start_encode(&app, &state, &pending, job.spec.encode_options()) {
+            Ok(session_id) => {
+                // The job may have been cancelled while its session started
+                let attached = queue.set_session(job.id, session_id);
+                if !matches!(attached, Ok(true)) {
+                    let _ = abort_encode(&state, &pending, session_id);
+                    attached?;
+                    continue;
+                }
+                job.session_id = Some(session_id);
+                queue::emit_status(&app, &job);
+                return Ok(Some(job));
+            }
+            Err(e) => {
+                let job = queue.complete(job.id, Err(e.to_string()))?;
+                queue::emit_status(&app, &job);
+            }
+        }
     }
+    Ok(None)
+}
+
+/// Finish the running job: finalise its encode, or abort it if the frontend
+/// reports `error` (e.g. the graph failed to load).
+#[tauri::command]
+fn complete_render_job(
+    app: AppHandle,
+    queue: State<RenderQueue>,
+    state: State<FfmpegState>,
+    pending: State<PendingOutputs>,
+    job_id: JobId,
+    error: Option<String>,
+) -> Result<RenderJob, EncodeError> {
+    let result = match (queue.get(job_id)?.session_id, error) {
+        (Some(session_id), Some(error)) => {
+            let _ = abort_encode(&state, &pending, session_id);
+            Err(error)
+        }
+        (Some(session_id), None) => stop_encode(&state, &pending, session_id)
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        (None, error) => error.map_or(Ok(()), Err),
+    };
+    let job = queue.complete(job_id, result)?;
+    queue::emit_status(&app, &job);
+    Ok(job)
+}
+
+// ── Frame cache ───────────────────────────────────────────────────────────────
 
-    session.stdin.write_all(&data).map_err(|e| format!("FFmpeg stdin write error: {e}"))?;
+/// Every cached render, most recently used first.
+#[tauri::command]
+fn list_frame_cache(app: AppHandle) -> Result<Vec<CacheEntry>, EncodeError> {
+    Ok(cache::list(&cache::root(&app)?))
+}
+
+#[tauri::command]
+fn delete_frame_cache_entry(app: AppHandle, key: CacheKey) -> Result<(), EncodeError> {
+    cache::remove(&cache::root(&app)?, &key)
+}
+
+/// Set the frame cache size limit in bytes (`None` for the default) and evict
+/// down to it straight away.
+#[tauri::command]
+fn set_frame_cache_limit(app: AppHandle, limit: Option<u64>) -> Result<(), EncodeError> {
+    let mut settings = settings::load(&app)?;
+    settings.frame_cache_limit = limit;
+    settings::save(&app, &settings)?;
+    cache::evict(&cache::root(&app)?, limit.unwrap_or(cache::DEFAULT_LIMIT));
     Ok(())
 }
 
-/// Close FFmpeg stdin and wait for the process to finish encoding.
+/// Encode a cached render with new `options` without rendering it again. Size,
+/// fps and input format come from the cache entry. Frames are fed from a
+/// background thread; progress arrives as `ffmpeg-progress` events and a
+/// `cache-reencode` event reports the result. Returns the session id, which
+/// `abort_ffmpeg_encode` accepts to cancel.
 #[tauri::command]
-fn stop_ffmpeg_encode(state: State<FfmpegState>) -> Result<(), String> {
-    let mut guard = state.0.lock().map_err(|e| e.to_string())?;
-    let session   = guard.take().ok_or("No active FFmpeg session")?;
+fn reencode_from_cache(
+    app: AppHandle,
+    state: State<FfmpegState>,
+    pending: State<PendingOutputs>,
+    key: CacheKey,
+    mut options: EncodeOptions,
+) -> Result<SessionId, EncodeError> {
+    let root   = cache::root(&app)?;
+    let reader = CacheReader::open(&root, &key)?;
+    let entry  = &reader.entry;
+    options.width           = key.width;
+    options.height          = key.height;
+    options.fps             = key.fps;
+    options.input_format    = entry.input_format;
+    options.expected_frames = Some(entry.frames);
+    options.motion_blur     = None;   // already averaged into the cached frames
+    options.cache           = None;
 
-    // Dropping stdin closes the pipe — FFmpeg will flush and exit cleanly
-    drop(session.stdin);
-    let status = session.child
-        .wait_with_output()
-        .map_err(|e| format!("FFmpeg wait error: {e}"))?;
+    let session_id = start_encode(&app, &state, &pending, options)?;
+    cache::touch(&root, &key);
 
-    if !status.status.success() {
-        return Err(format!("FFmpeg exited with code {:?}", status.status.code()));
+    std::thread::spawn(move || {
+        let state   = app.state::<FfmpegState>();
+        let pending = app.state::<PendingOutputs>();
+        let result  = feed_from_cache(&state, session_id, &reader)
+            .and_then(|()| stop_encode(&state, &pending, session_id));
+        if result.is_err() {
+            // Already gone if the frontend aborted it
+            let _ = abort_encode(&state, &pending, session_id);
+        }
+        // Lets the entry be evicted again
+        drop(reader);
+        let (frame_map, error) = match result {
+            Ok(map) => (Some(map), None),
+            Err(e)  => (None, Some(e)),
+        };
+        let _ = app.emit("cache-reencode", ReencodeFinished { session_id, frame_map, error });
+    });
+    Ok(session_id)
+}
+
+/// Queue every frame of a cache entry into a session, in order.
+fn feed_from_cache(
+    state: &FfmpegState,
+    session_id: SessionId,
+    reader: &CacheReader,
+) -> Result<(), EncodeError> {
+    let (sender, order) = {
+        let sessions = state.sessions.lock()?;
+        let session  = sessions.get(&session_id).ok_or(EncodeError::NoSession { session_id })?;
+        (session.writer.sender(), session.order.clone())
+    };
+    for index in 0..reader.entry.frames {
+        let frame = reader.read_frame(index)?;
+        for ready in order.lock()?.accept(index, frame)? {
+            sender.send(ready)?;
+        }
     }
     Ok(())
 }
 
+// ── Edit jobs ─────────────────────────────────────────────────────────────────
+
+/// Cut `options.start`–`options.end` out of an export. Runs in the background;
+/// progress arrives as `edit-progress` events. Returns the job id, or
+/// `SessionBusy` if an encode or another job is already writing the output.
+#[tauri::command]
+fn start_trim_job(
+    app: AppHandle,
+    state: State<EditState>,
+    options: TrimOptions,
+) -> Result<EditJobId, EncodeError> {
+    ensure_output_free(&app, &options.output_path)?;
+    let ffmpeg  = resolve_ffmpeg(&app)?;
+    let job_id  = state.next_id();
+    let command = options.command(&ffmpeg)?;
+    edit::spawn(&app, &ffmpeg, job_id, command, &options.output_path)?;
+    Ok(job_id)
+}
+
+/// Join several exports in order, optionally crossfading between them.
+#[tauri::command]
+fn start_concat_job(
+    app: AppHandle,
+    state: State<EditState>,
+    options: ConcatOptions,
+) -> Result<EditJobId, EncodeError> {
+    ensure_output_free(&app, &options.output_path)?;
+    let ffmpeg  = resolve_ffmpeg(&app)?;
+    let job_id  = state.next_id();
+    let command = options.command(&ffmpeg, job_id)?;
+    edit::spawn(&app, &ffmpeg, job_id, command, &options.output_path)?;
+    Ok(job_id)
+}
+
+/// Re-encode an export with other encoder settings.
+#[tauri::command]
+fn start_transcode_job(
+    app: AppHandle,
+    state: State<EditState>,
+    options: TranscodeOptions,
+) -> Result<EditJobId, EncodeError> {
+    ensure_output_free(&app, &options.output_path)?;
+    let ffmpeg  = resolve_ffmpeg(&app)?;
+    let job_id  = state.next_id();
+    let command = options.command(&ffmpeg)?;
+    edit::spawn(&app, &ffmpeg, job_id, command, &options.output_path)?;
+    Ok(job_id)
+}
+
+/// Stop an edit job; its temp output is removed and the final event has
+/// `cancelled` set.
+#[tauri::command]
+fn cancel_edit_job(state: State<EditState>, job_id: EditJobId) -> Result<(), EncodeError> {
+    state.cancel(job_id)
+}
+
 // ── App entry point ───────────────────────────────────────────────────────────
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -133,13 +1167,61 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
-        .manage(FfmpegState(Mutex::new(None)))
+        .manage(FfmpegState::default())
+        .manage(ProbeCache::default())
+        .manage(TileState::default())
+        .manage(EditState::default())
         .invoke_handler(tauri::generate_handler![
             start_ffmpeg_encode,
+            start_image_sequence,
             send_frame_rgba,
             stop_ffmpeg_encode,
+            abort_ffmpeg_encode,
+            list_encode_sessions,
+            get_encode_status,
+            preview_ffmpeg_command,
+            preflight_ffmpeg_encode,
+            list_encoder_presets,
+            save_encoder_preset,
+            delete_encoder_preset,
+            probe_ffmpeg,
+            get_ffmpeg_status,
+            set_ffmpeg_path,
+            start_tiled_still,
+            send_tile,
+            finish_tiled_still,
+            abort_tiled_still,
+            enqueue_render_job,
+            list_render_jobs,
+            pause_render_queue,
+            resume_render_queue,
+            move_render_job,
+            cancel_render_job,
+            clear_finished_render_jobs,
+            claim_render_job,
+            complete_render_job,
+            list_frame_cache,
+            delete_frame_cache_entry,
+            set_frame_cache_limit,
+            reencode_from_cache,
+            start_trim_job,
+            start_concat_job,
+            start_transcode_job,
+            cancel_edit_job,
         ])
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::Destroyed = event {
+                window.state::<FfmpegState>().abort_all();
+                window.state::<TileState>().abort_all();
+                let edits = window.state::<EditState>();
+                edits.cancel_all();
+                // Edit jobs remove their own temp files once FFmpeg has exited
+                window.state::<PendingOutputs>().clear_except(&edits.temp_paths());
+            }
+        })
         .setup(|app| {
+            app.manage(PendingOutputs::recover(app.handle()));
+            app.manage(RenderQueue::load(app.handle()));
             if cfg!(debug_assertions) {
                 app.handle().plugin(
                     tauri_plugin_log::Builder::default()
@@ -152,3 +1234,14 @@ pub fn run() {
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_must_be_raw_bodies() {
+        assert_eq!(raw_frame(&InvokeBody::Raw(vec![1, 2, 3])).unwrap(), [1, 2, 3]);
+        assert!(raw_frame(&InvokeBody::Json(serde_json::json!({ "data": [1, 2, 3] }))).is_err());
+    }
+}
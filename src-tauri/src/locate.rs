@@ -0,0 +1,109 @@
+//! FFmpeg binary resolution.
+//!
+//! Sources are tried in order, so machines without network access can still
+//! encode as long as an FFmpeg exists somewhere:
+//!
+//! 1. the path saved with `set_ffmpeg_path`
+//! 2. the `SHADER_STUDIO_FFMPEG` environment variable
+//! 3. `ffmpeg` on `PATH`
+//! 4. a sidecar build ffmpeg-sidecar downloaded earlier
+//! 5. downloading a sidecar build now
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::error::EncodeError;
+use crate::settings;
+
+/// Environment variable naming an FFmpeg binary.
+pub const FFMPEG_ENV: &str = "SHADER_STUDIO_FFMPEG";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FfmpegSource {
+    Configured,
+    Env,
+    Path,
+    Sidecar,
+    Downloaded,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedFfmpeg {
+    pub path: PathBuf,
+    pub source: FfmpegSource,
+}
+
+/// Find FFmpeg, falling back to a download only if `allow_download` is set.
+pub fn resolve(app: &AppHandle, allow_download: bool) -> Result<Option<ResolvedFfmpeg>, EncodeError> {
+    let found = |path: PathBuf, source| Some(ResolvedFfmpeg { path, source });
+
+    if let Some(path) = settings::load(app)?.ffmpeg_path {
+        // An explicit choice that has gone missing is an error, not a reason to fall back
+        if !path.is_file() {
+            return Err(EncodeError::FfmpegNotFound { path: Some(path.display().to_string()) });
+        }
+        return Ok(found(path, FfmpegSource::Configured));
+    }
+    if let Some(path) = env::var_os(FFMPEG_ENV).map(PathBuf::from) {
+        if !path.is_file() {
+            return Err(EncodeError::FfmpegNotFound { path: Some(path.display().to_string()) });
+        }
+        return Ok(found(path, FfmpegSource::Env));
+    }
+    if let Some(path) = find_on_path() {
+        return Ok(found(path, FfmpegSource::Path));
+    }
+    if let Some(path) = existing_sidecar() {
+        return Ok(found(path, FfmpegSource::Sidecar));
+    }
+    if !allow_download {
+        return Ok(None);
+    }
+    ffmpeg_sidecar::download::auto_download()
+        .map_err(|e| EncodeError::DownloadFailed { message: e.to_string() })?;
+    let path = existing_sidecar().ok_or(EncodeError::DownloadFailed {
+        message: "download finished but the binary is missing".into(),
+    })?;
+    Ok(found(path, FfmpegSource::Downloaded))
+}
+
+/// Run `path -version` and return the version string if it's really FFmpeg.
+pub fn validate(path: &Path) -> Result<String, EncodeError> {
+    let out = Command::new(path).arg("-version").output().map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => EncodeError::FfmpegNotFound { path: Some(path.display().to_string()) },
+        _ => EncodeError::Io { message: format!("Failed to run {}: {e}", path.display()) },
+    })?;
+    String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .next()
+        .and_then(|l| l.strip_prefix("ffmpeg version "))
+        .and_then(|l| l.split_whitespace().next())
+        .map(str::to_string)
+        .ok_or_else(|| format!("{} is not an FFmpeg binary", path.display()).into())
+}
+
+fn find_on_path() -> Option<PathBuf> {
+    let name = format!("ffmpeg{}", env::consts::EXE_SUFFIX);
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(&name))
+        .find(|p| p.is_file())
+}
+
+fn existing_sidecar() -> Option<PathBuf> {
+    ffmpeg_sidecar::paths::sidecar_path().ok().filter(|p| p.is_file())
+}
+
+/// Reported by `get_ffmpeg_status`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FfmpegStatus {
+    /// The binary that would be used, or None if FFmpeg still has to be downloaded.
+    pub resolved: Option<ResolvedFfmpeg>,
+    pub version: Option<String>,
+}
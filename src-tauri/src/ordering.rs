@@ -0,0 +1,269 @@
+//! Frame-indexed delivery.
+//!
+//! Each frame sent with `send_frame_rgba` may carry its index in the
+//! `x-frame-index` header. Frames that arrive early are held until the ones
+//! before them turn up; repeats of an index already seen are dropped. If a
+//! frame is still missing once `MAX_HELD_FRAMES` later ones are waiting, or
+//! when the session stops, the gap is handled by the session's `GapPolicy`.
+//! A gap longer than `MAX_GAP_FRAMES` is never filled, and an index past the
+//! expected length is rejected.
+//!
+//! With motion blur the index counts sub-frames, as sent.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::EncodeError;
+use crate::writer::FrameSink;
+
+/// Early frames held while waiting for a missing one before giving up on it.
+pub const MAX_HELD_FRAMES: usize = 8;
+
+/// Longest run of missing frames one gap may fill before the session fails.
+pub const MAX_GAP_FRAMES: u64 = 1024;
+
+/// Header carrying a frame's index alongside its raw body.
+pub const FRAME_INDEX_HEADER: &str = "x-frame-index";
+
+/// What to do about a frame that never arrived.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GapPolicy {
+    /// Write the previous frame again in its place.
+    #[default]
+    Repeat,
+    /// Stop the session with `EncodeError::MissingFrame`.
+    Fail,
+}
+
+/// Per-session account of how frames were delivered, returned by
+/// `stop_ffmpeg_encode`.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrameMap {
+    /// Frames written in order, including repeats.
+    pub frames: u64,
+    /// Indices that never arrived and were filled with the previous frame.
+    pub repeated: Vec<u64>,
+    /// Indices received more than once; only the first copy was written.
+    pub duplicates: Vec<u64>,
+    /// Indices that arrived after their slot had been filled, and were dropped.
+    pub late: Vec<u64>,
+    /// Why the render wasn't stored in the frame cache, if caching was requested.
+    pub cache_warning: Option<String>,
+}
+
+/// Reorders incoming frames by index. Frames handed back are ready to queue,
+/// in order; an empty frame stands for "repeat the previous one" (see
+/// `repeat_previous`).
+pub struct FrameOrder {
+    policy: GapPolicy,
+    /// Frames the session expects, if known.
+    total: Option<u64>,
+    next: u64,
+    held: BTreeMap<u64, Vec<u8>>,
+    map: FrameMap,
+}
+
+impl FrameOrder {
+    pub fn new(policy: GapPolicy, total: Option<u64>) -> Self {
+        Self { policy, total, next: 0, held: BTreeMap::new(), map: FrameMap::default() }
+    }
+
+    /// Accept frame `index`, returning whatever can now be written.
+    pub fn accept(&mut self, index: u64, frame: Vec<u8>) -> Result<Vec<Vec<u8>>, EncodeError> {
+        if let Some(total) = self.total.filter(|&total| index >= total) {
+            return Err(format!("Frame index {index} is past the expected {total} frames").into());
+        }
+        if index < self.next && self.map.repeated.binary_search(&index).is_ok() {
+            self.map.late.push(index);
+            return Ok(Vec::new());
+        }
+        if index < self.next || self.held.contains_key(&index) {
+            self.map.duplicates.push(index);
+            return Ok(Vec::new());
+        }
+        self.held.insert(index, frame);
+        let mut ready = Vec::new();
+        self.release(&mut ready);
+        if self.held.len() > MAX_HELD_FRAMES {
+            // The missing frame is too far behind to still be coming
+            let first = *self.held.keys().next().expect("held is non-empty");
+            self.fill_to(first, &mut ready)?;
+            self.release(&mut ready);
+        }
+        Ok(ready)
+    }
+
+    /// Flush held frames at the end of a session, filling gaps between them and,
+    /// if the total is known, up to it.
+    pub fn finish(&mut self) -> Result<Vec<Vec<u8>>, EncodeError> {
+        let mut ready = Vec::new();
+        while let Some(&first) = self.held.keys().next() {
+            self.fill_to(first, &mut ready)?;
+            self.release(&mut ready);
+        }
+        if let Some(total) = self.total {
+            self.fill_to(total, &mut ready)?;
+        }
+        Ok(ready)
+    }
+
+    pub fn map(&self) -> FrameMap {
+        FrameMap { frames: self.next, ..self.map.clone() }
+    }
+
+    /// Move consecutive held frames starting at `next` into `ready`.
+    fn release(&mut self, ready: &mut Vec<Vec<u8>>) {
+        while let Some(frame) = self.held.remove(&self.next) {
+            ready.push(frame);
+            self.next += 1;
+        }
+    }
+
+    /// Deal with every missing index below `end` according to the policy.
+    fn fill_to(&mut self, end: u64, ready: &mut Vec<Vec<u8>>) -> Result<(), EncodeError> {
+        if self.next >= end {
+            return Ok(());
+        }
+        // Nothing to repeat before the first frame
+        if self.policy == GapPolicy::Fail || self.next == 0 || end - self.next > MAX_GAP_FRAMES {
+            return Err(EncodeError::MissingFrame { index: self.next });
+        }
+        for index in self.next..end {
+            self.map.repeated.push(index);
+            ready.push(Vec::new());
+        }
+        self.next = end;
+        Ok(())
+    }
+}
+
+/// Wrap `sink` so an empty frame writes the last real frame again. Keeps one
+/// copy of the latest frame on the writer thread.
+pub fn repeat_previous(mut sink: FrameSink) -> FrameSink {
+    let mut last: Vec<u8> = Vec::new();
+    Box::new(move |frame: &[u8]| {
+        if frame.is_empty() {
+            return sink(&last);
+        }
+        last.clear();
+        last.extend_from_slice(frame);
+        sink(frame)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Accept each index with a one-byte frame holding it; return what was released.
+    fn feed(order: &mut FrameOrder, indices: &[u64]) -> Vec<Vec<u8>> {
+        let mut out = Vec::new();
+        for &i in indices {
+            out.extend(order.accept(i, vec![i as u8]).unwrap());
+        }
+        out
+    }
+
+    #[test]
+    fn reorders_early_frames() {
+        let mut order = FrameOrder::new(GapPolicy::Repeat, None);
+        let out = feed(&mut order, &[1, 2, 0, 3]);
+        assert_eq!(out, vec![vec![0], vec![1], vec![2], vec![3]]);
+        assert_eq!(order.map().frames, 4);
+    }
+
+    #[test]
+    fn drops_duplicates() {
+        let mut order = FrameOrder::new(GapPolicy::Repeat, None);
+        let out = feed(&mut order, &[0, 2, 2, 0, 1]);
+        assert_eq!(out.len(), 3);
+        assert_eq!(order.map().duplicates, vec![2, 0]);
+        assert!(order.map().late.is_empty());
+    }
+
+    #[test]
+    fn repeats_frames_that_never_arrive() {
+        let mut order = FrameOrder::new(GapPolicy::Repeat, None);
+        let held: Vec<u64> = (2..=MAX_HELD_FRAMES as u64 + 2).collect();
+        let mut out = feed(&mut order, &[0]);
+        out.extend(feed(&mut order, &held));
+        // Index 1 is given up on once too many later frames are waiting
+        assert_eq!(out[1], Vec::<u8>::new());
+        assert_eq!(order.map().repeated, vec![1]);
+        assert_eq!(out.len(), held.len() + 2);
+    }
+
+    #[test]
+    fn reports_frames_arriving_after_their_slot_was_filled() {
+        let mut order = FrameOrder::new(GapPolicy::Repeat, None);
+        let held: Vec<u64> = (2..=MAX_HELD_FRAMES as u64 + 2).collect();
+        feed(&mut order, &[0]);
+        feed(&mut order, &held);
+        assert!(feed(&mut order, &[1]).is_empty());
+        assert_eq!(order.map().late, vec![1]);
+        assert!(order.map().duplicates.is_empty());
+    }
+
+    #[test]
+    fn finish_fills_up_to_the_total() {
+        let mut order = FrameOrder::new(GapPolicy::Repeat, Some(5));
+        feed(&mut order, &[0, 2]);
+        let out = order.finish().unwrap();
+        assert_eq!(out, vec![vec![], vec![2], vec![], vec![]]);
+        assert_eq!(order.map().repeated, vec![1, 3, 4]);
+        assert_eq!(order.map().frames, 5);
+    }
+
+    #[test]
+    fn fail_policy_reports_the_missing_frame() {
+        let mut order = FrameOrder::new(GapPolicy::Fail, None);
+        feed(&mut order, &[0, 2]);
+        assert!(matches!(order.finish(), Err(EncodeError::MissingFrame { index: 1 })));
+    }
+
+    #[test]
+    fn cannot_repeat_before_the_first_frame() {
+        let mut order = FrameOrder::new(GapPolicy::Repeat, None);
+        feed(&mut order, &[1]);
+        assert!(matches!(order.finish(), Err(EncodeError::MissingFrame { index: 0 })));
+    }
+
+    #[test]
+    fn rejects_indices_past_the_total() {
+        let mut order = FrameOrder::new(GapPolicy::Repeat, Some(10));
+        assert!(matches!(order.accept(10, vec![0]), Err(EncodeError::Invalid { .. })));
+        assert!(order.accept(9, vec![0]).is_ok());
+    }
+
+    #[test]
+    fn refuses_to_fill_huge_gaps() {
+        let mut order = FrameOrder::new(GapPolicy::Repeat, None);
+        feed(&mut order, &[0]);
+        let far = MAX_GAP_FRAMES + 10;
+        let mut result = Ok(Vec::new());
+        for i in far..far + MAX_HELD_FRAMES as u64 + 1 {
+            result = order.accept(i, vec![0]);
+            if result.is_err() {
+                break;
+            }
+        }
+        assert!(matches!(result, Err(EncodeError::MissingFrame { index: 1 })));
+    }
+
+    #[test]
+    fn repeat_previous_writes_the_last_frame_again() {
+        let written = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink_log = written.clone();
+        let mut sink = repeat_previous(Box::new(move |frame: &[u8]| {
+            sink_log.lock().unwrap().push(frame.to_vec());
+            Ok(())
+        }));
+        sink(&[1, 2]).unwrap();
+        sink(&[]).unwrap();
+        sink(&[3, 4]).unwrap();
+        assert_eq!(*written.lock().unwrap(), vec![vec![1, 2], vec![1, 2], vec![3, 4]]);
+    }
+}
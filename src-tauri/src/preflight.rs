@@ -0,0 +1,226 @@
+//! Output checks run before FFmpeg is spawned, and free-space monitoring while
+//! a session writes.
+//!
+//! The size estimate is deliberately rough — bits per pixel per codec at its
+//! default quality, or the requested bitrate — and is only used to refuse
+//! encodes that clearly won't fit and to warn about ones that might not.
+
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::encode::EncodeOptions;
+use crate::error::EncodeError;
+use crate::writer::FrameSink;
+
+/// Space always left free, both when checking up front and while encoding.
+pub const FREE_SPACE_RESERVE: u64 = 256 * 1024 * 1024;
+
+/// How often the writer thread re-reads free space.
+const FREE_SPACE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Approximate output bits per pixel at each codec's default settings.
+const BITS_PER_PIXEL: &[(&str, f64)] = &[
+    ("h264",   0.15),
+    ("vp9",    0.10),
+    ("av1",    0.07),
+    ("prores", 5.0),
+    ("ffv1",   12.0),
+    ("qtrle",  16.0),
+    ("png",    14.0),
+    ("gif",    2.0),
+];
+
+/// What `preflight_ffmpeg_encode` reports back.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Preflight {
+    /// Estimated output size; None when the render length isn't known.
+    pub estimated_bytes: Option<u64>,
+    /// Free space in the output directory, if the platform reports it.
+    pub free_bytes: Option<u64>,
+    pub warnings: Vec<String>,
+}
+
+/// Check that `options.output_path` can be written and is likely to fit.
+/// Fails for a missing or read-only directory, or when the estimate plus
+/// `FREE_SPACE_RESERVE` exceeds the free space; tighter calls become warnings.
+pub fn check(options: &EncodeOptions) -> Result<Preflight, EncodeError> {
+    let output = Path::new(&options.output_path);
+    let dir    = output_dir(output);
+    if !dir.is_dir() {
+        return Err(EncodeError::OutputNotWritable {
+            path: options.output_path.clone(),
+            message: format!("Directory not found: {}", dir.display()),
+        });
+    }
+    check_writable(&dir).map_err(|message| EncodeError::OutputNotWritable {
+        path: options.output_path.clone(),
+        message,
+    })?;
+
+    let mut warnings = Vec::new();
+    if output.exists() {
+        warnings.push(format!("{} already exists and will be overwritten", output.display()));
+    }
+
+    let estimated_bytes = estimate_bytes(options)?;
+    let free_bytes      = fs2::available_space(&dir).ok();
+    match (estimated_bytes, free_bytes) {
+        (Some(estimate), Some(free)) => {
+            let required = estimate + FREE_SPACE_RESERVE;
+            if required > free {
+                return Err(EncodeError::InsufficientSpace {
+                    path: options.output_path.clone(),
+                    required_bytes: required,
+                    free_bytes: free,
+                    kept_path: None,
+                });
+            }
+            if estimate * 2 > free - FREE_SPACE_RESERVE {
+                warnings.push(format!(
+                    "Output may use {} of the {} free — the estimate is rough",
+                    format_bytes(estimate), format_bytes(free),
+                ));
+            }
+        }
+        (None, _) => warnings.push("Render length unknown, so output size wasn't estimated".into()),
+        (_, None) => warnings.push("Couldn't read free space for the output directory".into()),
+    }
+
+    Ok(Preflight { estimated_bytes, free_bytes, warnings })
+}
+
+/// Estimated output size from the bitrate, or from codec, resolution, fps and
+/// `expected_frames`.
+fn estimate_bytes(options: &EncodeOptions) -> Result<Option<u64>, String> {
+    let Some(frames) = options.expected_frames else {
+        return Ok(None);
+    };
+    let seconds = frames as f64 / options.fps.max(1) as f64;
+    if let Some(bitrate) = &options.encoder.bitrate {
+        return Ok(Some((parse_bitrate(bitrate)? * seconds / 8.0) as u64));
+    }
+    // Filters may scale, pad or crop before encoding
+    let (width, height) = options.output_size()?;
+    let bpp = BITS_PER_PIXEL
+        .iter()
+        .find(|(codec, _)| *codec == options.encoder.codec)
+        .map_or(1.0, |(_, bpp)| *bpp);
+    let bits = width as f64 * height as f64 * frames as f64 * bpp;
+    Ok(Some((bits / 8.0) as u64))
+}
+
+/// `8M`, `800k` or a plain number, in bits per second.
+fn parse_bitrate(bitrate: &str) -> Result<f64, String> {
+    let bad = || format!("Invalid bitrate: {bitrate:?}");
+    let bitrate = bitrate.trim();
+    let (number, scale) = match bitrate.char_indices().last().ok_or_else(bad)? {
+        (i, 'k' | 'K') => (&bitrate[..i], 1e3),
+        (i, 'm' | 'M') => (&bitrate[..i], 1e6),
+        (i, 'g' | 'G') => (&bitrate[..i], 1e9),
+        _              => (bitrate, 1.0),
+    };
+    number.parse::<f64>().map(|n| n * scale).map_err(|_| bad())
+}
+
+/// Create and remove a probe file — permission bits alone miss ACLs and
+/// read-only mounts. Each call gets its own name so concurrent preflights
+/// don't collide.
+fn check_writable(dir: &Path) -> Result<(), String> {
+    static NEXT_PROBE: AtomicU32 = AtomicU32::new(0);
+    let n     = NEXT_PROBE.fetch_add(1, Ordering::Relaxed);
+    let probe = dir.join(format!(".shader-studio-preflight-{}-{n}", std::process::id()));
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .map_err(|e| format!("Can't write to {}: {e}", dir.display()))?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(())
+}
+
+fn output_dir(output: &Path) -> PathBuf {
+    match output.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const MIB: f64 = 1024.0 * 1024.0;
+    let mib = bytes as f64 / MIB;
+    if mib >= 1024.0 {
+        format!("{:.1} GiB", mib / 1024.0)
+    } else {
+        format!("{mib:.0} MiB")
+    }
+}
+
+/// Wrap `sink` so it stops with an error once free space in `dir` drops below
+/// `FREE_SPACE_RESERVE`. The writer then rejects further frames, and
+/// `stop_ffmpeg_encode` still lets FFmpeg finalise what was written.
+pub fn watch_free_space(mut sink: FrameSink, dir: PathBuf) -> FrameSink {
+    let mut last_check: Option<Instant> = None;
+    Box::new(move |frame: &[u8]| {
+        let due = match last_check {
+            Some(t) => t.elapsed() >= FREE_SPACE_CHECK_INTERVAL,
+            None    => true,
+        };
+        if due {
+            last_check = Some(Instant::now());
+            if let Ok(free) = fs2::available_space(&dir) {
+                if free < FREE_SPACE_RESERVE {
+                    return Err(EncodeError::InsufficientSpace {
+                        path: dir.display().to_string(),
+                        required_bytes: FREE_SPACE_RESERVE,
+                        free_bytes: free,
+                        kept_path: None,
+                    });
+                }
+            }
+        }
+        sink(frame)
+    })
+}
+
+/// Directory holding `output_path`, for `watch_free_space`.
+pub fn watch_dir(output_path: &str) -> PathBuf {
+    output_dir(Path::new(output_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_bitrates() {
+        assert_eq!(parse_bitrate("8M").unwrap(), 8e6);
+        assert_eq!(parse_bitrate("800k").unwrap(), 8e5);
+        assert_eq!(parse_bitrate(" 1.5G ").unwrap(), 1.5e9);
+        assert_eq!(parse_bitrate("128000").unwrap(), 128000.0);
+        for bad in ["", "M", "fast", "8MB"] {
+            assert!(parse_bitrate(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn concurrent_write_probes_dont_collide() {
+        let dir = std::env::temp_dir().join(format!("shader-studio-preflight-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let probes: Vec<_> = (0..8)
+            .map(|_| {
+                let dir = dir.clone();
+                std::thread::spawn(move || check_writable(&dir))
+            })
+            .collect();
+        for probe in probes {
+            assert_eq!(probe.join().unwrap(), Ok(()));
+        }
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        let _ = std::fs::remove_dir(&dir);
+    }
+}
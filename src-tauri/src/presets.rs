@@ -0,0 +1,27 @@
+//! User-defined encoder presets, stored as JSON in the app config dir.
+
+use std::collections::BTreeMap;
+
+use tauri::AppHandle;
+
+use crate::codec::EncoderSettings;
+use crate::config;
+use crate::error::EncodeError;
+
+const PRESETS_FILE: &str = "encoder-presets.json";
+
+pub type Presets = BTreeMap<String, EncoderSettings>;
+
+/// All saved presets, keyed by name. A missing file means no presets yet.
+pub fn load(app: &AppHandle) -> Result<Presets, EncodeError> {
+    config::load(&config::config_path(app, PRESETS_FILE)?)
+}
+
+pub fn save(app: &AppHandle, presets: &Presets) -> Result<(), EncodeError> {
+    config::save(&config::config_path(app, PRESETS_FILE)?, presets)
+}
+
+/// Look up a preset by name.
+pub fn get(app: &AppHandle, name: &str) -> Result<EncoderSettings, EncodeError> {
+    load(app)?.remove(name).ok_or_else(|| format!("No encoder preset named {name:?}").into())
+}
@@ -0,0 +1,161 @@
+//! FFmpeg capability probing.
+//!
+//! Runs the resolved binary with `-version`, `-encoders`, `-pix_fmts` and
+//! `-muxers` and checks each codec `start_ffmpeg_encode` offers against the
+//! result, so the UI can hide options a stripped FFmpeg build can't encode.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+use crate::codec::{self, ALPHA_CODECS, VIDEO_CODECS};
+use crate::error::EncodeError;
+use crate::locate;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FfmpegProbe {
+    pub ffmpeg_path: PathBuf,
+    pub version: String,
+    pub encoders: BTreeSet<String>,
+    pub pix_fmts: BTreeSet<String>,
+    pub muxers: BTreeSet<String>,
+    pub codecs: Vec<CodecSupport>,
+}
+
+/// Whether one codec option can be used with the probed binary.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodecSupport {
+    pub codec: String,
+    pub encoder: String,
+    pub has_encoder: bool,
+    pub has_muxer: bool,
+    /// Every required pixel format is available for opaque output.
+    pub opaque: bool,
+    /// Alpha output is possible (codec carries alpha and its pixel format exists).
+    pub alpha: bool,
+}
+
+/// Last probe result, reused until the binary changes or a refresh is asked for.
+#[derive(Default)]
+pub struct ProbeCache(pub Mutex<Option<FfmpegProbe>>);
+
+/// Probe the binary at `ffmpeg_path`.
+pub fn probe(ffmpeg_path: &Path) -> Result<FfmpegProbe, EncodeError> {
+    let version = locate::validate(ffmpeg_path)?;
+
+    let encoders = parse_table(&run(ffmpeg_path, "-encoders")?, " ------");
+    let pix_fmts = parse_table(&run(ffmpeg_path, "-pix_fmts")?, "-----");
+    let muxers   = parse_table(&run(ffmpeg_path, "-muxers")?, " --");
+
+    let codecs = VIDEO_CODECS
+        .iter()
+        .chain(std::iter::once(&"gif"))
+        .map(|&c| codec_support(c, &encoders, &pix_fmts, &muxers))
+        .collect();
+
+    Ok(FfmpegProbe {
+        ffmpeg_path: ffmpeg_path.to_path_buf(),
+        version,
+        encoders,
+        pix_fmts,
+        muxers,
+        codecs,
+    })
+}
+
+fn codec_support(
+    codec: &str,
+    encoders: &BTreeSet<String>,
+    pix_fmts: &BTreeSet<String>,
+    muxers: &BTreeSet<String>,
+) -> CodecSupport {
+    // GIF picks its own palette format, so only needs the encoder
+    let pix_fmt_ok = |alpha: bool| {
+        codec == "gif"
+            || codec::video_args(codec, alpha)
+                .ok()
+                .and_then(|args| codec::arg_value(&args, "-pix_fmt").map(|f| pix_fmts.contains(f)))
+                .unwrap_or(false)
+    };
+    let encoder = codec::encoder_name(codec);
+    CodecSupport {
+        codec: codec.to_string(),
+        encoder: encoder.to_string(),
+        has_encoder: encoders.contains(encoder),
+        has_muxer: muxers.contains(codec::muxer_name(codec)),
+        opaque: pix_fmt_ok(false),
+        alpha: ALPHA_CODECS.contains(&codec) && pix_fmt_ok(true),
+    }
+}
+
+fn run(ffmpeg_path: &Path, flag: &str) -> Result<String, EncodeError> {
+    let out = Command::new(ffmpeg_path)
+        .args(["-hide_banner", flag])
+        .output()
+        .map_err(|e| EncodeError::Io {
+            message: format!("Failed to run {} {flag}: {e}", ffmpeg_path.display()),
+        })?;
+    if !out.status.success() {
+        return Err(EncodeError::NonZeroExit {
+            code: out.status.code(),
+            log_tail: String::from_utf8_lossy(&out.stderr).into_owned(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+/// Names from FFmpeg's `-encoders`/`-pix_fmts`/`-muxers` listings: each row
+/// after the `separator` line is `FLAGS name[,alias…] description`.
+fn parse_table(output: &str, separator: &str) -> BTreeSet<String> {
+    output
+        .lines()
+        .skip_while(|l| !l.starts_with(separator))
+        .skip(1)
+        .filter_map(|l| l.split_whitespace().nth(1))
+        .flat_map(|names| names.split(','))
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ffmpeg_listings() {
+        let output = "\
+Encoders:
+ V..... = Video
+ ------
+ V....D libx264              libx264 H.264 / AVC / MPEG-4 AVC
+ V....D prores_ks            Apple ProRes (iCodec Pro)
+ V....D png,apng             PNG image
+";
+        let names: Vec<String> = parse_table(output, " ------").into_iter().collect();
+        assert_eq!(names, ["apng", "libx264", "png", "prores_ks"]);
+    }
+
+    #[test]
+    fn missing_separator_lists_nothing() {
+        assert!(parse_table("Encoders:\n V....D libx264 H.264\n", " ------").is_empty());
+    }
+
+    #[test]
+    fn codec_support_needs_the_alpha_pixel_format() {
+        let set = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<BTreeSet<_>>();
+        let encoders = set(&["prores_ks"]);
+        let muxers   = set(&["mov"]);
+        let support  = codec_support("prores", &encoders, &set(&["yuv422p10le"]), &muxers);
+        assert!(support.has_encoder && support.has_muxer && support.opaque);
+        assert!(!support.alpha);
+        let support  = codec_support("prores", &encoders, &set(&["yuv422p10le", "yuva444p10le"]), &muxers);
+        assert!(support.alpha);
+        let support  = codec_support("h264", &encoders, &set(&["yuv420p"]), &muxers);
+        assert!(!support.has_encoder && !support.has_muxer && !support.alpha);
+    }
+}
@@ -0,0 +1,201 @@
+//! FFmpeg progress and log capture.
+//!
+//! FFmpeg is started with `-progress pipe:1`, which makes it write blocks of
+//! `key=value` lines to stdout, each block terminated by `progress=continue`
+//! (or `progress=end`). stderr carries the regular log. Both are drained on
+//! background threads: progress blocks become `ffmpeg-progress` events and the
+//! log is kept in a bounded ring buffer so errors can quote its tail. Edit jobs
+//! (see `edit`) read their progress with the same parser.
+
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::SessionId;
+
+/// Number of stderr lines kept per session.
+const LOG_CAPACITY: usize = 200;
+/// Number of recent warnings included in each progress event.
+const WARNING_CAPACITY: usize = 5;
+
+/// FFmpeg log lines attached to pipe and exit errors.
+pub const LOG_TAIL_LINES: usize = 20;
+
+/// Event emitted to the frontend once per FFmpeg progress block.
+pub const PROGRESS_EVENT: &str = "ffmpeg-progress";
+
+#[derive(Default)]
+struct LogBuffer {
+    lines: VecDeque<String>,
+    warnings: VecDeque<String>,
+}
+
+/// Shared, bounded view of an FFmpeg process's stderr.
+#[derive(Clone, Default)]
+pub struct FfmpegLog(Arc<Mutex<LogBuffer>>);
+
+impl FfmpegLog {
+    fn push(&self, line: String) {
+        let Ok(mut buf) = self.0.lock() else { return };
+        let lower = line.to_ascii_lowercase();
+        if lower.contains("warning") || lower.contains("error") {
+            if buf.warnings.len() == WARNING_CAPACITY {
+                buf.warnings.pop_front();
+            }
+            buf.warnings.push_back(line.clone());
+        }
+        if buf.lines.len() == LOG_CAPACITY {
+            buf.lines.pop_front();
+        }
+        buf.lines.push_back(line);
+    }
+
+    fn warnings(&self) -> Vec<String> {
+        self.0.lock().map(|b| b.warnings.iter().cloned().collect()).unwrap_or_default()
+    }
+
+    /// The last `n` log lines joined with newlines (empty if nothing was logged).
+    pub fn tail(&self, n: usize) -> String {
+        let Ok(buf) = self.0.lock() else { return String::new() };
+        let skip = buf.lines.len().saturating_sub(n);
+        buf.lines.iter().skip(skip).cloned().collect::<Vec<_>>().join("\n")
+    }
+}
+
+/// Payload of `ffmpeg-progress`.
+#[derive(Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodeProgress {
+    pub session_id: SessionId,
+    pub frame: u64,
+    pub fps: f32,
+    /// e.g. "1843.2kbits/s", or "N/A" before the first packet is written.
+    pub bitrate: String,
+    /// Bytes written to the output so far.
+    pub total_size: u64,
+    pub out_time_us: i64,
+    /// Encode speed relative to real time, e.g. 2.5 for "2.5x".
+    pub speed: f32,
+    /// Set on the final event, after FFmpeg has flushed its output.
+    pub done: bool,
+    pub warnings: Vec<String>,
+}
+
+/// The latest values of one `-progress` block.
+#[derive(Clone, Default)]
+pub struct ProgressBlock {
+    pub frame: u64,
+    pub fps: f32,
+    pub bitrate: String,
+    pub total_size: u64,
+    pub out_time_us: i64,
+    pub speed: f32,
+}
+
+impl ProgressBlock {
+    /// Apply one `key=value` line. Returns `Some(end)` when the line closes a
+    /// block, `end` being set for the last one.
+    pub fn update(&mut self, line: &str) -> Option<bool> {
+        let (key, value) = line.split_once('=')?;
+        let value = value.trim();
+        match key.trim() {
+            "frame"       => self.frame = value.parse().unwrap_or(self.frame),
+            "fps"         => self.fps = value.parse().unwrap_or(self.fps),
+            "bitrate"     => self.bitrate = value.to_string(),
+            "total_size"  => self.total_size = value.parse().unwrap_or(self.total_size),
+            "out_time_us" => self.out_time_us = value.parse().unwrap_or(self.out_time_us),
+            "speed"       => self.speed = value.trim_end_matches('x').parse().unwrap_or(self.speed),
+            "progress"    => return Some(value == "end"),
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Read `-progress` output until FFmpeg closes it, calling `on_block` after
+/// every block with the values so far and whether it was the last.
+pub fn read_progress(stdout: impl Read, mut on_block: impl FnMut(&ProgressBlock, bool)) {
+    let mut block = ProgressBlock::default();
+    for line in BufReader::new(stdout).lines() {
+        let Ok(line) = line else { break };
+        if let Some(end) = block.update(&line) {
+            on_block(&block, end);
+        }
+    }
+}
+
+/// Drain stderr into `log`, one line at a time.
+pub fn spawn_log_reader(stderr: impl Read + Send + 'static, log: FfmpegLog) -> JoinHandle<()> {
+    thread::spawn(move || {
+        for line in BufReader::new(stderr).lines() {
+            let Ok(line) = line else { break };
+            let line = line.trim_end().to_string();
+            if !line.is_empty() {
+                log.push(line);
+            }
+        }
+    })
+}
+
+/// Parse `-progress` blocks from stdout and emit one event per block.
+pub fn spawn_progress_reader(
+    stdout: impl Read + Send + 'static,
+    app: AppHandle,
+    session_id: SessionId,
+    log: FfmpegLog,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        read_progress(stdout, |block, end| {
+            let _ = app.emit(PROGRESS_EVENT, EncodeProgress {
+                session_id,
+                frame: block.frame,
+                fps: block.fps,
+                bitrate: block.bitrate.clone(),
+                total_size: block.total_size,
+                out_time_us: block.out_time_us,
+                speed: block.speed,
+                done: end,
+                warnings: log.warnings(),
+            });
+        });
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_progress_blocks() {
+        let output = "frame=10\nfps=29.5\nbitrate=N/A\nout_time_us=333333\nspeed=1.5x\nprogress=continue\n\
+                      frame=20\nspeed=bad\ntotal_size=4096\nprogress=end\n";
+        let mut blocks = Vec::new();
+        read_progress(output.as_bytes(), |block, end| blocks.push((block.clone(), end)));
+        assert_eq!(blocks.len(), 2);
+        let (first, end) = &blocks[0];
+        assert!(!end);
+        assert_eq!((first.frame, first.fps, first.out_time_us, first.speed), (10, 29.5, 333333, 1.5));
+        assert_eq!(first.bitrate, "N/A");
+        // Unparseable values keep the previous one
+        let (last, end) = &blocks[1];
+        assert!(end);
+        assert_eq!((last.frame, last.total_size, last.speed), (20, 4096, 1.5));
+    }
+
+    #[test]
+    fn log_keeps_a_bounded_tail_and_recent_warnings() {
+        let log = FfmpegLog::default();
+        assert_eq!(log.tail(LOG_TAIL_LINES), "");
+        for i in 0..LOG_CAPACITY + 10 {
+            log.push(format!("line {i}"));
+        }
+        log.push("Warning: odd dimensions".into());
+        assert_eq!(log.tail(2), format!("line {}\nWarning: odd dimensions", LOG_CAPACITY + 9));
+        assert_eq!(log.0.lock().unwrap().lines.len(), LOG_CAPACITY);
+        assert_eq!(log.warnings(), ["Warning: odd dimensions"]);
+    }
+}
@@ -0,0 +1,359 @@
+//! Background render queue.
+//!
+//! Jobs are persisted as JSON in the app data dir and run one at a time. The
+//! frontend still does the rendering: it claims the next job with
+//! `claim_render_job`, which starts the job's encode session, sends its frames,
+//! and reports back with `complete_render_job`. Pausing stops further claims;
+//! the running job is left to finish. A job that was running when the app quit
+//! is queued again on the next start.
+//!
+//! Every status change is emitted as a `render-job` event.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::codec::EncoderSettings;
+use crate::config;
+use crate::encode::EncodeOptions;
+use crate::error::EncodeError;
+use crate::frame::FrameFormat;
+use crate::ordering::GapPolicy;
+use crate::SessionId;
+
+const QUEUE_FILE: &str = "render-queue.json";
+
+pub type JobId = u32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+/// What to render, as passed to `enqueue_render_job`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenderJobSpec {
+    /// Name of the saved graph to render — loaded by the frontend worker.
+    pub graph: String,
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    /// Length in seconds.
+    pub duration: f64,
+    #[serde(default)]
+    pub encoder: EncoderSettings,
+    #[serde(default)]
+    pub preserve_alpha: bool,
+    pub output_path: String,
+}
+
+impl RenderJobSpec {
+    pub fn total_frames(&self) -> u64 {
+        (self.duration * self.fps as f64).ceil().max(0.0) as u64
+    }
+
+    pub fn encode_options(&self) -> EncodeOptions {
+        EncodeOptions {
+            output_path: self.output_path.clone(),
+            width: self.width,
+            height: self.height,
+            fps: self.fps,
+            encoder: self.encoder.clone(),
+            preset: None,
+            preserve_alpha: self.preserve_alpha,
+            audio: None,
+            gif: None,
+            filters: Vec::new(),
+            motion_blur: None,
+            input_format: FrameFormat::Rgba8,
+            hdr: None,
+            expected_frames: Some(self.total_frames()),
+            gap_policy: GapPolicy::default(),
+            cache: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenderJob {
+    pub id: JobId,
+    #[serde(flatten)]
+    pub spec: RenderJobSpec,
+    pub status: JobStatus,
+    pub error: Option<String>,
+    /// Encode session of the running job; not restored on load.
+    #[serde(skip_deserializing)]
+    pub session_id: Option<SessionId>,
+}
+
+/// The queue as stored on disk and returned by `list_render_jobs`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueSnapshot {
+    pub paused: bool,
+    pub jobs: Vec<RenderJob>,
+    next_id: JobId,
+}
+
+/// Payload of the `render-job` event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenderJobEvent {
+    pub job_id: JobId,
+    pub status: JobStatus,
+    pub error: Option<String>,
+}
+
+pub struct RenderQueue {
+    /// None if the app data dir couldn't be resolved; the queue then isn't saved.
+    file: Option<PathBuf>,
+    inner: Mutex<QueueSnapshot>,
+}
+
+impl RenderQueue {
+    /// Load the saved queue, re-queueing the job that was running at exit.
+    pub fn load(app: &AppHandle) -> Self {
+        let file = app.path().app_data_dir().ok().map(|dir| dir.join(QUEUE_FILE));
+        let mut queue: QueueSnapshot = match file.as_deref().map(config::load).transpose() {
+            Ok(queue) => queue.unwrap_or_default(),
+            Err(e)    => {
+                log::warn!("Ignoring unreadable render queue: {e}");
+                QueueSnapshot::default()
+            }
+        };
+        for job in &mut queue.jobs {
+            if job.status == JobStatus::Running {
+                job.status = JobStatus::Queued;
+            }
+        }
+        Self { file, inner: Mutex::new(queue) }
+    }
+
+    pub fn snapshot(&self) -> Result<QueueSnapshot, EncodeError> {
+        Ok(self.inner.lock()?.clone())
+    }
+
+    pub fn enqueue(&self, spec: RenderJobSpec) -> Result<RenderJob, EncodeError> {
+        if spec.fps == 0 || spec.duration <= 0.0 {
+            return Err("Render jobs need a positive fps and duration".into());
+        }
+        // Catch bad encoder settings now rather than when the job comes up
+        spec.encode_options().ffmpeg_args()?;
+        let mut queue = self.inner.lock()?;
+        let job = RenderJob {
+            id: queue.next_id,
+            spec,
+            status: JobStatus::Queued,
+            error: None,
+            session_id: None,
+        };
+        queue.next_id += 1;
+        queue.jobs.push(job.clone());
+        self.save(&queue)?;
+        Ok(job)
+    }
+
+    pub fn set_paused(&self, paused: bool) -> Result<(), EncodeError> {
+        let mut queue = self.inner.lock()?;
+        queue.paused = paused;
+        self.save(&queue)
+    }
+
+    /// Move a job to `position` in the list (clamped to the end).
+    pub fn move_job(&self, id: JobId, position: usize) -> Result<(), EncodeError> {
+        let mut queue = self.inner.lock()?;
+        let from = find(&queue, id)?;
+        let job  = queue.jobs.remove(from);
+        let to   = position.min(queue.jobs.len());
+        queue.jobs.insert(to, job);
+        self.save(&queue)
+    }
+
+    /// Mark a queued or running job cancelled. Returns the job, whose
+    /// `session_id` the caller must abort if it was running.
+    pub fn cancel(&self, id: JobId) -> Result<RenderJob, EncodeError> {
+        let mut queue = self.inner.lock()?;
+        let index = find(&queue, id)?;
+        let job   = &mut queue.jobs[index];
+        if !matches!(job.status, JobStatus::Queued | JobStatus::Running) {
+            return Ok(job.clone());
+        }
+        let session_id = job.session_id.take();
+        job.status = JobStatus::Cancelled;
+        let job = RenderJob { session_id, ..job.clone() };
+        self.save(&queue)?;
+        Ok(job)
+    }
+
+    /// Drop finished, failed and cancelled jobs from the list.
+    pub fn clear_finished(&self) -> Result<(), EncodeError> {
+        let mut queue = self.inner.lock()?;
+        queue.jobs.retain(|j| matches!(j.status, JobStatus::Queued | JobStatus::Running));
+        self.save(&queue)
+    }
+
+    /// Mark the first queued job running — unless the queue is paused or a job
+    /// is already running.
+    pub fn claim_next(&self) -> Result<Option<RenderJob>, EncodeError> {
+        let mut queue = self.inner.lock()?;
+        if queue.paused || queue.jobs.iter().any(|j| j.status == JobStatus::Running) {
+            return Ok(None);
+        }
+        let Some(job) = queue.jobs.iter_mut().find(|j| j.status == JobStatus::Queued) else {
+            return Ok(None);
+        };
+        job.status = JobStatus::Running;
+        job.error  = None;
+        let job = job.clone();
+        self.save(&queue)?;
+        Ok(Some(job))
+    }
+
+    pub fn get(&self, id: JobId) -> Result<RenderJob, EncodeError> {
+        let queue = self.inner.lock()?;
+        Ok(queue.jobs[find(&queue, id)?].clone())
+    }
+
+    /// Attach `session_id` to a running job. Returns false if the job was
+    /// cancelled or cleared meanwhile; the caller must then abort the session.
+    pub fn set_session(&self, id: JobId, session_id: SessionId) -> Result<bool, EncodeError> {
+        let mut queue = self.inner.lock()?;
+        match queue.jobs.iter_mut().find(|j| j.id == id && j.status == JobStatus::Running) {
+            Some(job) => {
+                job.session_id = Some(session_id);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Record how a running job ended. Jobs that are no longer running (e.g.
+    /// cancelled meanwhile) are left as they are.
+    pub fn complete(&self, id: JobId, result: Result<(), String>) -> Result<RenderJob, EncodeError> {
+        let mut queue = self.inner.lock()?;
+        let index = find(&queue, id)?;
+        let job   = &mut queue.jobs[index];
+        if job.status != JobStatus::Running {
+            return Ok(job.clone());
+        }
+        job.session_id = None;
+        match result {
+            Ok(())     => job.status = JobStatus::Done,
+            Err(error) => {
+                job.status = JobStatus::Failed;
+                job.error  = Some(error);
+            }
+        }
+        let job = job.clone();
+        self.save(&queue)?;
+        Ok(job)
+    }
+
+    fn save(&self, queue: &QueueSnapshot) -> Result<(), EncodeError> {
+        match &self.file {
+            Some(file) => config::save(file, queue),
+            None       => Ok(()),
+        }
+    }
+}
+
+fn find(queue: &QueueSnapshot, id: JobId) -> Result<usize, EncodeError> {
+    queue.jobs
+        .iter()
+        .position(|j| j.id == id)
+        .ok_or_else(|| format!("No render job {id}").into())
+}
+
+/// Emit a `render-job` event for `job`'s current status.
+pub fn emit_status(app: &AppHandle, job: &RenderJob) {
+    let _ = app.emit("render-job", RenderJobEvent {
+        job_id: job.id,
+        status: job.status,
+        error: job.error.clone(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue() -> RenderQueue {
+        RenderQueue { file: None, inner: Mutex::default() }
+    }
+
+    fn spec(output_path: &str) -> RenderJobSpec {
+        RenderJobSpec {
+            graph: "plasma".into(),
+            width: 64,
+            height: 64,
+            fps: 30,
+            duration: 1.0,
+            encoder: EncoderSettings { codec: "h264".into(), ..Default::default() },
+            preserve_alpha: false,
+            output_path: output_path.into(),
+        }
+    }
+
+    #[test]
+    fn claims_one_job_at_a_time_in_order() {
+        let queue = queue();
+        let first = queue.enqueue(spec("a.mp4")).unwrap();
+        let next  = queue.enqueue(spec("b.mp4")).unwrap();
+        assert!(queue.enqueue(RenderJobSpec { fps: 0, ..spec("c.mp4") }).is_err());
+
+        assert_eq!(queue.claim_next().unwrap().map(|j| j.id), Some(first.id));
+        assert!(queue.claim_next().unwrap().is_none());
+        assert_eq!(queue.complete(first.id, Ok(())).unwrap().status, JobStatus::Done);
+
+        queue.set_paused(true).unwrap();
+        assert!(queue.claim_next().unwrap().is_none());
+        queue.set_paused(false).unwrap();
+        assert_eq!(queue.claim_next().unwrap().map(|j| j.id), Some(next.id));
+        let failed = queue.complete(next.id, Err("boom".into())).unwrap();
+        assert_eq!((failed.status, failed.error.as_deref()), (JobStatus::Failed, Some("boom")));
+        assert!(queue.claim_next().unwrap().is_none());
+    }
+
+    #[test]
+    fn cancelling_hands_back_the_running_session() {
+        let queue = queue();
+        let job   = queue.enqueue(spec("a.mp4")).unwrap();
+        queue.claim_next().unwrap();
+        assert!(queue.set_session(job.id, 7).unwrap());
+
+        let cancelled = queue.cancel(job.id).unwrap();
+        assert_eq!((cancelled.status, cancelled.session_id), (JobStatus::Cancelled, Some(7)));
+        assert_eq!(queue.get(job.id).unwrap().session_id, None);
+        // The worker reporting back afterwards doesn't revive it
+        assert_eq!(queue.complete(job.id, Ok(())).unwrap().status, JobStatus::Cancelled);
+        assert_eq!(queue.cancel(job.id).unwrap().session_id, None);
+        // A session started while it was being cancelled isn't attached
+        assert!(!queue.set_session(job.id, 8).unwrap());
+
+        queue.clear_finished().unwrap();
+        assert!(queue.snapshot().unwrap().jobs.is_empty());
+        assert!(queue.cancel(job.id).is_err());
+    }
+
+    #[test]
+    fn moves_jobs_within_the_list() {
+        let queue = queue();
+        let ids: Vec<JobId> = ["a.mp4", "b.mp4", "c.mp4"]
+            .iter()
+            .map(|p| queue.enqueue(spec(p)).unwrap().id)
+            .collect();
+        queue.move_job(ids[2], 0).unwrap();
+        queue.move_job(ids[0], 99).unwrap();
+        let order: Vec<JobId> = queue.snapshot().unwrap().jobs.iter().map(|j| j.id).collect();
+        assert_eq!(order, [ids[2], ids[1], ids[0]]);
+    }
+}
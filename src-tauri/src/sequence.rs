@@ -0,0 +1,237 @@
+//! Numbered image-sequence output, encoded in Rust without FFmpeg.
+//!
+//! Frames are written as `<dir>/<pattern>.<ext>`, where the pattern holds one
+//! printf-style frame number placeholder (`%d` or `%04d`), e.g. `shot_%04d`.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use image::{ImageBuffer, ImageFormat as ImageCrateFormat, Rgba};
+use serde::Deserialize;
+
+use crate::accumulate::MotionBlur;
+use crate::error::EncodeError;
+use crate::frame::FrameFormat;
+use crate::ordering::GapPolicy;
+use crate::writer::FrameSink;
+
+/// What the frontend passes to `start_image_sequence`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SequenceOptions {
+    /// Existing directory the frames are written into.
+    pub output_dir: String,
+    /// File name with a frame number placeholder, e.g. `shot_%04d`.
+    pub pattern: String,
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub format: ImageFormat,
+    /// Number of the first frame.
+    #[serde(default)]
+    pub start_number: u64,
+    /// Average several sub-frames into each written frame.
+    pub motion_blur: Option<MotionBlur>,
+    /// Layout of the frames sent with `send_frame_rgba`.
+    #[serde(default)]
+    pub input_format: FrameFormat,
+    /// Frames the frontend will send; enables the ETA in `get_encode_status`.
+    pub expected_frames: Option<u64>,
+    /// How frames that never arrive are handled (see `ordering`).
+    #[serde(default)]
+    pub gap_policy: GapPolicy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    /// PNG with alpha — 8-bit, or 16-bit for `rgba16`/`rgba32f` input.
+    Png,
+    /// 16-bit-per-channel RGBA TIFF.
+    Tiff16,
+    /// Half-float RGBA OpenEXR. Integer input is linearised from sRGB; float
+    /// input is written as-is.
+    Exr,
+}
+
+impl ImageFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png    => "png",
+            ImageFormat::Tiff16 => "tiff",
+            ImageFormat::Exr    => "exr",
+        }
+    }
+}
+
+/// Where and how the frames of one sequence are written.
+#[derive(Debug, Clone)]
+pub struct ImageSequence {
+    dir: PathBuf,
+    prefix: String,
+    suffix: String,
+    /// Zero-padding width of the frame number (0 = none).
+    pad: usize,
+    start_number: u64,
+    pub format: ImageFormat,
+    input_format: FrameFormat,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ImageSequence {
+    pub fn new(options: &SequenceOptions) -> Result<Self, EncodeError> {
+        let dir = PathBuf::from(&options.output_dir);
+        if !dir.is_dir() {
+            return Err(EncodeError::OutputNotWritable {
+                path: options.output_dir.clone(),
+                message: "Directory not found".into(),
+            });
+        }
+        let (prefix, pad, suffix) = parse_pattern(&options.pattern)?;
+        Ok(Self {
+            dir,
+            prefix,
+            suffix,
+            pad,
+            start_number: options.start_number,
+            format: options.format,
+            input_format: options.input_format,
+            width: options.width,
+            height: options.height,
+        })
+    }
+
+    /// Path of the `index`-th frame written (0-based, before `start_number` is applied).
+    pub fn path_for(&self, index: u64) -> PathBuf {
+        let number = self.start_number + index;
+        let name = format!(
+            "{}{:0pad$}{}.{}",
+            self.prefix, number, self.suffix, self.format.extension(),
+            pad = self.pad,
+        );
+        self.dir.join(name)
+    }
+
+    /// A writer-thread sink that encodes each frame to the next numbered file.
+    pub fn sink(&self) -> FrameSink {
+        let sequence = self.clone();
+        let mut index = 0;
+        Box::new(move |frame| {
+            let path = sequence.path_for(index);
+            sequence.write_frame(&path, frame)?;
+            index += 1;
+            Ok(())
+        })
+    }
+
+    /// Remove the first `count` frames — used to clean up an aborted sequence.
+    pub fn remove_frames(&self, count: u64) {
+        for index in 0..count {
+            let _ = std::fs::remove_file(self.path_for(index));
+        }
+    }
+
+    fn write_frame(&self, path: &Path, data: &[u8]) -> Result<(), EncodeError> {
+        let (w, h) = (self.width, self.height);
+        let err = |e: &dyn std::fmt::Display| EncodeError::Io {
+            message: format!("Failed to write {}: {e}", path.display()),
+        };
+        match (self.format, self.input_format) {
+            (ImageFormat::Png, FrameFormat::Rgba8) => {
+                let img = ImageBuffer::<Rgba<u8>, _>::from_raw(w, h, data)
+                    .ok_or("Frame buffer too small")?;
+                img.save_with_format(path, ImageCrateFormat::Png).map_err(|e| err(&e))
+            }
+            // Deep input keeps its precision as a 16-bit PNG
+            (ImageFormat::Png, input) => {
+                let img = ImageBuffer::<Rgba<u16>, _>::from_raw(w, h, input.to_rgba16(data))
+                    .ok_or("Frame buffer too small")?;
+                img.save_with_format(path, ImageCrateFormat::Png).map_err(|e| err(&e))
+            }
+            (ImageFormat::Tiff16, input) => {
+                let img = ImageBuffer::<Rgba<u16>, _>::from_raw(w, h, input.to_rgba16(data))
+                    .ok_or("Frame buffer too small")?;
+                let mut out = BufWriter::new(File::create(path).map_err(|e| err(&e))?);
+                img.write_to(&mut out, ImageCrateFormat::Tiff).map_err(|e| err(&e))
+            }
+            (ImageFormat::Exr, input) => {
+                use exr::prelude::{write_rgba_file, f16};
+                let linear = input.to_linear_f32(data);
+                if linear.len() < w as usize * h as usize * 4 {
+                    return Err("Frame buffer too small".into());
+                }
+                let px = |x: usize, y: usize| {
+                    let i = (y * w as usize + x) * 4;
+                    (
+                        f16::from_f32(linear[i]),
+                        f16::from_f32(linear[i + 1]),
+                        f16::from_f32(linear[i + 2]),
+                        f16::from_f32(linear[i + 3]),
+                    )
+                };
+                write_rgba_file(path, w as usize, h as usize, px).map_err(|e| err(&e))
+            }
+        }
+    }
+}
+
+/// Split `shot_%04d` into ("shot_", 4, "").
+fn parse_pattern(pattern: &str) -> Result<(String, usize, String), String> {
+    let bad = || format!("Name pattern must contain one %d or %0Nd placeholder: {pattern:?}");
+    let start = pattern.find('%').ok_or_else(bad)?;
+    let rest  = &pattern[start + 1..];
+    let end   = rest.find('d').ok_or_else(bad)?;
+    let spec  = &rest[..end];
+    let pad = if spec.is_empty() {
+        0
+    } else if spec.starts_with('0') && spec.len() > 1 {
+        spec[1..].parse().map_err(|_| bad())?
+    } else {
+        return Err(bad());
+    };
+    let suffix = &rest[end + 1..];
+    if suffix.contains('%') || pattern.contains(['/', '\\']) {
+        return Err(bad());
+    }
+    Ok((pattern[..start].to_string(), pad, suffix.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_placeholders() {
+        assert_eq!(parse_pattern("shot_%04d").unwrap(), ("shot_".into(), 4, String::new()));
+        assert_eq!(parse_pattern("%d_beauty").unwrap(), (String::new(), 0, "_beauty".into()));
+    }
+
+    #[test]
+    fn rejects_bad_patterns() {
+        for pattern in ["shot", "shot_%4d", "shot_%0xd", "%d_%d", "dir/%04d", "a\\%d", "shot_%"] {
+            assert!(parse_pattern(pattern).is_err(), "{pattern}");
+        }
+    }
+
+    #[test]
+    fn numbers_files_from_the_start_number() {
+        let options = SequenceOptions {
+            output_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+            pattern: "shot_%04d_beauty".into(),
+            width: 2,
+            height: 2,
+            fps: 24,
+            format: ImageFormat::Exr,
+            start_number: 1001,
+            motion_blur: None,
+            input_format: FrameFormat::Rgba8,
+            expected_frames: None,
+            gap_policy: GapPolicy::default(),
+        };
+        let sequence = ImageSequence::new(&options).unwrap();
+        assert_eq!(sequence.path_for(0), std::env::temp_dir().join("shot_1001_beauty.exr"));
+        assert_eq!(sequence.path_for(9), std::env::temp_dir().join("shot_1010_beauty.exr"));
+    }
+}
@@ -0,0 +1,30 @@
+//! App settings stored as JSON in the app config dir.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::config;
+use crate::error::EncodeError;
+
+const SETTINGS_FILE: &str = "settings.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Settings {
+    /// FFmpeg binary chosen by the user; takes priority over every other source.
+    pub ffmpeg_path: Option<PathBuf>,
+    /// Size limit for the frame cache in bytes; `cache::DEFAULT_LIMIT` if unset.
+    #[serde(default)]
+    pub frame_cache_limit: Option<u64>,
+}
+
+/// Saved settings, or the defaults if none have been saved yet.
+pub fn load(app: &AppHandle) -> Result<Settings, EncodeError> {
+    config::load(&config::config_path(app, SETTINGS_FILE)?)
+}
+
+pub fn save(app: &AppHandle, settings: &Settings) -> Result<(), EncodeError> {
+    config::save(&config::config_path(app, SETTINGS_FILE)?, settings)
+}
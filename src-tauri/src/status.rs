@@ -0,0 +1,63 @@
+//! Frame accounting and ETA for `get_encode_status`.
+
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::SessionId;
+
+/// Minimum gap between samples used for the current frame rate, so two
+/// status polls in quick succession don't produce a noisy reading.
+const MIN_SAMPLE_SECS: f64 = 0.5;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodeStatus {
+    pub session_id: SessionId,
+    /// Frames accepted by `send_frame_rgba` (sub-frames, with motion blur).
+    pub frames_received: u64,
+    /// Frames the writer thread has handed to the encoder.
+    pub frames_written: u64,
+    /// Raw frame bytes handed to the encoder.
+    pub bytes_written: u64,
+    pub queue_depth: usize,
+    pub elapsed_secs: f64,
+    /// Written frames per second since the session started.
+    pub average_fps: f64,
+    /// Written frames per second over the last sampling interval.
+    pub current_fps: f64,
+    /// Total output frames given at start, if any.
+    pub expected_frames: Option<u64>,
+    /// Seconds until `expected_frames` are written, at the current rate.
+    pub eta_secs: Option<f64>,
+}
+
+/// Tracks when a session started and its recent write rate.
+pub struct FrameClock {
+    started: Instant,
+    sample_at: Instant,
+    sample_frames: u64,
+    current_fps: f64,
+}
+
+impl FrameClock {
+    pub fn start() -> Self {
+        let now = Instant::now();
+        Self { started: now, sample_at: now, sample_frames: 0, current_fps: 0.0 }
+    }
+
+    /// Update the rate sample with the writer's total and return
+    /// (elapsed secs, average fps, current fps).
+    pub fn sample(&mut self, frames_written: u64) -> (f64, f64, f64) {
+        let now     = Instant::now();
+        let elapsed = now.duration_since(self.started).as_secs_f64();
+        let window  = now.duration_since(self.sample_at).as_secs_f64();
+        if window >= MIN_SAMPLE_SECS {
+            self.current_fps   = (frames_written - self.sample_frames) as f64 / window;
+            self.sample_at     = now;
+            self.sample_frames = frames_written;
+        }
+        let average = if elapsed > 0.0 { frames_written as f64 / elapsed } else { 0.0 };
+        (elapsed, average, self.current_fps)
+    }
+}
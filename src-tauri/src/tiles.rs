@@ -0,0 +1,350 @@
+//! Tiled still rendering for images larger than WebGL's drawing buffer.
+//!
+//! The frontend renders the image tile by tile with an offset projection and
+//! sends each tile (top-down RGBA8, row-major tile order) with `send_tile`.
+//! Tiles of the current tile row are copied into a band buffer; once a row is
+//! complete the band is streamed to an encoder thread and the buffer reused,
+//! so memory stays at one tile row no matter how large the output is.
+//!
+//! The image is written to a temp file (see `atomic`) and only moved onto
+//! `output_path` once complete, so an existing file survives a failed render.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicU32;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+
+use serde::{Deserialize, Serialize};
+
+use crate::atomic;
+use crate::error::EncodeError;
+use crate::SessionId;
+
+/// Event emitted after every accepted tile.
+pub const TILE_PROGRESS_EVENT: &str = "tile-progress";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StillFormat {
+    Png,
+    Tiff,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TiledStillOptions {
+    pub output_path: String,
+    /// Full image size.
+    pub width: u32,
+    pub height: u32,
+    /// Tile size; edge tiles are cropped to the image.
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub format: StillFormat,
+    /// Print resolution written into the file's metadata.
+    #[serde(default = "default_dpi")]
+    pub dpi: u32,
+}
+
+fn default_dpi() -> u32 { 300 }
+
+/// Payload of `tile-progress`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TileProgress {
+    pub session_id: SessionId,
+    pub tiles_done: u32,
+    pub tiles_total: u32,
+}
+
+pub struct TiledStill {
+    options: TiledStillOptions,
+    temp_path: PathBuf,
+    cols: u32,
+    rows: u32,
+    /// Tile row currently being filled.
+    row: u32,
+    /// Which tiles of `row` have arrived.
+    received: Vec<bool>,
+    band: Vec<u8>,
+    tiles_done: u32,
+    tx: Option<SyncSender<Vec<u8>>>,
+    handle: Option<JoinHandle<Result<(), EncodeError>>>,
+}
+
+#[derive(Default)]
+pub struct TileState {
+    pub sessions: Mutex<HashMap<SessionId, TiledStill>>,
+    pub next_id: AtomicU32,
+}
+
+impl TiledStill {
+    pub fn start(options: TiledStillOptions, session_id: SessionId) -> Result<Self, EncodeError> {
+        let o = &options;
+        if o.width == 0 || o.height == 0 || o.tile_width == 0 || o.tile_height == 0 {
+            return Err("Image and tile sizes must be non-zero".into());
+        }
+        if o.dpi == 0 {
+            return Err("DPI must be non-zero".into());
+        }
+        let cols = o.width.div_ceil(o.tile_width);
+        let rows = o.height.div_ceil(o.tile_height);
+
+        let temp_path = atomic::temp_path(Path::new(&o.output_path), format!("tile-{session_id}"));
+        let file = File::create(&temp_path).map_err(|e| EncodeError::OutputNotWritable {
+            path: o.output_path.clone(),
+            message: e.to_string(),
+        })?;
+        // One band in flight while the next is being filled
+        let (tx, rx) = mpsc::sync_channel(1);
+        let handle = {
+            let options = options.clone();
+            thread::spawn(move || encode_bands(options, file, rx))
+        };
+
+        let first = Self::band_len(&options, 0);
+        Ok(Self {
+            cols,
+            rows,
+            row: 0,
+            received: vec![false; cols as usize],
+            band: vec![0; first],
+            tiles_done: 0,
+            tx: Some(tx),
+            handle: Some(handle),
+            options,
+            temp_path,
+        })
+    }
+
+    fn band_len(o: &TiledStillOptions, row: u32) -> usize {
+        let rows = o.tile_height.min(o.height - row * o.tile_height);
+        rows as usize * o.width as usize * 4
+    }
+
+    pub fn output_path(&self) -> &str {
+        &self.options.output_path
+    }
+
+    pub fn temp_path(&self) -> &Path {
+        &self.temp_path
+    }
+
+    pub fn tiles_total(&self) -> u32 {
+        self.cols * self.rows
+    }
+
+    /// Copy tile (`x`, `y`) into the current band; stream the band out once
+    /// its row is complete. Returns the number of tiles accepted so far.
+    pub fn add_tile(&mut self, x: u32, y: u32, data: &[u8]) -> Result<u32, EncodeError> {
+        let o = &self.options;
+        if x >= self.cols || y >= self.rows {
+            return Err(format!("Tile ({x}, {y}) is outside the {}x{} grid", self.cols, self.rows).into());
+        }
+        if y != self.row {
+            return Err(format!("Tiles must arrive row by row: expected row {}, got {y}", self.row).into());
+        }
+        if self.received[x as usize] {
+            return Err(format!("Tile ({x}, {y}) was already sent").into());
+        }
+
+        let tw = o.tile_width.min(o.width - x * o.tile_width) as usize;
+        let th = o.tile_height.min(o.height - y * o.tile_height) as usize;
+        if data.len() != tw * th * 4 {
+            return Err(EncodeError::FrameSizeMismatch { got: data.len(), expected: tw * th * 4 });
+        }
+
+        let stride = o.width as usize * 4;
+        let left   = x as usize * o.tile_width as usize * 4;
+        for (r, src) in data.chunks_exact(tw * 4).enumerate() {
+            let dst = r * stride + left;
+            self.band[dst..dst + tw * 4].copy_from_slice(src);
+        }
+        self.received[x as usize] = true;
+        self.tiles_done += 1;
+
+        if self.received.iter().all(|&r| r) {
+            self.row += 1;
+            let next = if self.row < self.rows { Self::band_len(o, self.row) } else { 0 };
+            let band = std::mem::replace(&mut self.band, vec![0; next]);
+            self.received.fill(false);
+            let sent = self.tx.as_ref().is_some_and(|tx| tx.send(band).is_ok());
+            if !sent {
+                // The encoder thread stopped — surface its error
+                self.tx = None;
+                return Err(self.join_encoder().err().unwrap_or_else(|| EncodeError::Io {
+                    message: "Tile encoder has stopped".into(),
+                }));
+            }
+        }
+        Ok(self.tiles_done)
+    }
+
+    fn join_encoder(&mut self) -> Result<(), EncodeError> {
+        let stopped = |message: &str| EncodeError::Io { message: message.into() };
+        match self.handle.take() {
+            Some(h) => h.join().map_err(|_| stopped("Tile encoder thread panicked"))?,
+            None    => Err(stopped("Tile encoder has stopped")),
+        }
+    }
+
+    /// Wait for the encoder to write the last band, then move the image onto
+    /// `output_path`.
+    pub fn finish(mut self) -> Result<(), EncodeError> {
+        if self.tiles_done != self.tiles_total() {
+            let missing = self.tiles_total() - self.tiles_done;
+            self.abort();
+            return Err(format!("Tiled still incomplete: {missing} tiles missing").into());
+        }
+        drop(self.tx.take());
+        match self.join_encoder() {
+            Ok(()) => atomic::commit(&self.temp_path, Path::new(&self.options.output_path)),
+            Err(e) => {
+                let _ = std::fs::remove_file(&self.temp_path);
+                Err(e)
+            }
+        }
+    }
+
+    /// Stop encoding and delete the partial file; `output_path` is untouched.
+    pub fn abort(mut self) {
+        drop(self.tx.take());
+        let _ = self.join_encoder();
+        let _ = std::fs::remove_file(&self.temp_path);
+    }
+}
+
+/// Encoder thread: writes each band of rows as it arrives.
+fn encode_bands(o: TiledStillOptions, file: File, rx: Receiver<Vec<u8>>) -> Result<(), EncodeError> {
+    let err = |e: &dyn std::fmt::Display| EncodeError::Io {
+        message: format!("Failed to write {}: {e}", o.output_path),
+    };
+    let out = BufWriter::new(file);
+    match o.format {
+        StillFormat::Png => {
+            let mut encoder = png::Encoder::new(out, o.width, o.height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            // pHYs stores pixels per metre
+            let ppm = (o.dpi as f64 / 0.0254).round() as u32;
+            encoder.set_pixel_dims(Some(png::PixelDimensions {
+                xppu: ppm,
+                yppu: ppm,
+                unit: png::Unit::Meter,
+            }));
+            let mut stream = encoder
+                .write_header().map_err(|e| err(&e))?
+                .into_stream_writer().map_err(|e| err(&e))?;
+            for band in rx {
+                stream.write_all(&band).map_err(|e| err(&e))?;
+            }
+            stream.finish().map_err(|e| err(&e))
+        }
+        StillFormat::Tiff => {
+            use tiff::encoder::{colortype, Rational, TiffEncoder};
+            use tiff::tags::ResolutionUnit;
+
+            let mut tiff  = TiffEncoder::new(out).map_err(|e| err(&e))?;
+            let mut image = tiff
+                .new_image::<colortype::RGBA8>(o.width, o.height)
+                .map_err(|e| err(&e))?;
+            image.resolution(ResolutionUnit::Inch, Rational { n: o.dpi, d: 1 });
+            // One strip per band, so each band is written as soon as it arrives
+            image.rows_per_strip(o.tile_height).map_err(|e| err(&e))?;
+            for band in rx {
+                image.write_strip(&band).map_err(|e| err(&e))?;
+            }
+            image.finish().map_err(|e| err(&e))
+        }
+    }
+}
+
+/// Parse the tile coordinate headers sent with `send_tile`.
+pub fn tile_coords(headers: &tauri::http::HeaderMap) -> Result<(u32, u32), String> {
+    let get = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+            .ok_or_else(|| format!("Missing or invalid {name} header"))
+    };
+    Ok((get("x-tile-x")?, get("x-tile-y")?))
+}
+
+impl TileState {
+    /// Abort every tiled still in progress (window closed mid-render).
+    pub fn abort_all(&self) {
+        let stills: Vec<TiledStill> = match self.sessions.lock() {
+            Ok(mut s)   => s.drain().map(|(_, s)| s).collect(),
+            Err(poison) => poison.into_inner().drain().map(|(_, s)| s).collect(),
+        };
+        for still in stills {
+            still.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// RGBA pixels of the `w`×`h` tile at `left`,`top`, each coloured by its
+    /// position in the full image.
+    fn tile(left: u32, top: u32, w: u32, h: u32) -> Vec<u8> {
+        (top..top + h)
+            .flat_map(|y| (left..left + w).flat_map(move |x| [x as u8, y as u8, 7, 255]))
+            .collect()
+    }
+
+    fn start(name: &str, width: u32, height: u32) -> (TiledStill, PathBuf) {
+        let output = std::env::temp_dir().join(format!("shader-studio-{name}-{}.png", std::process::id()));
+        let still  = TiledStill::start(TiledStillOptions {
+            output_path: output.to_string_lossy().into_owned(),
+            width,
+            height,
+            tile_width: 2,
+            tile_height: 2,
+            format: StillFormat::Png,
+            dpi: 300,
+        }, 0).unwrap();
+        (still, output)
+    }
+
+    #[test]
+    fn stitches_tiles_including_cropped_edges() {
+        let (mut still, output) = start("tiles-stitch", 3, 3);
+        assert_eq!(still.tiles_total(), 4);
+        assert_eq!(still.add_tile(1, 0, &tile(2, 0, 1, 2)).unwrap(), 1);
+        assert_eq!(still.add_tile(0, 0, &tile(0, 0, 2, 2)).unwrap(), 2);
+        still.add_tile(0, 1, &tile(0, 2, 2, 1)).unwrap();
+        still.add_tile(1, 1, &tile(2, 2, 1, 1)).unwrap();
+        still.finish().unwrap();
+
+        let image = image::open(&output).unwrap().to_rgba8();
+        std::fs::remove_file(&output).unwrap();
+        assert_eq!(image.dimensions(), (3, 3));
+        assert_eq!(image.into_raw(), tile(0, 0, 3, 3));
+    }
+
+    #[test]
+    fn rejects_bad_tiles() {
+        let (mut still, output) = start("tiles-reject", 3, 3);
+        let temp = still.temp_path().to_path_buf();
+        assert!(matches!(
+            still.add_tile(0, 0, &tile(0, 0, 1, 1)),
+            Err(EncodeError::FrameSizeMismatch { got: 4, expected: 16 }),
+        ));
+        assert!(still.add_tile(2, 0, &tile(0, 0, 1, 1)).is_err());
+        assert!(still.add_tile(0, 1, &tile(0, 2, 2, 1)).is_err());
+        still.add_tile(0, 0, &tile(0, 0, 2, 2)).unwrap();
+        assert!(still.add_tile(0, 0, &tile(0, 0, 2, 2)).is_err());
+
+        // Finishing with tiles missing fails and leaves nothing behind
+        assert!(still.finish().is_err());
+        assert!(!temp.exists() && !output.exists());
+    }
+}
@@ -0,0 +1,198 @@
+//! Background frame writer.
+//!
+//! Each session owns one writer thread fed by a bounded channel, so
+//! `send_frame_rgba` only copies the frame into a recycled buffer and queues
+//! it. The thread hands frames to the session's sink — FFmpeg stdin or an
+//! image-sequence encoder. When the sink falls behind the queue fills up and
+//! senders block, which is the backpressure the render loop sees. Buffers
+//! travel back to the session after being written so steady-state encoding
+//! doesn't allocate.
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::error::EncodeError;
+
+/// Frames that may wait in the queue before senders block.
+pub const FRAME_QUEUE_CAPACITY: usize = 4;
+
+type WriteError = Arc<Mutex<Option<EncodeError>>>;
+
+/// Consumes one frame at a time on the writer thread. Dropped when the writer
+/// finishes, which for FFmpeg closes stdin.
+pub type FrameSink = Box<dyn FnMut(&[u8]) -> Result<(), EncodeError> + Send>;
+
+pub struct FrameWriter {
+    tx: SyncSender<Vec<u8>>,
+    pool: Receiver<Vec<u8>>,
+    depth: Arc<AtomicUsize>,
+    stats: Arc<WriteStats>,
+    error: WriteError,
+    handle: JoinHandle<()>,
+}
+
+/// Running totals of what the sink has accepted.
+#[derive(Default)]
+pub struct WriteStats {
+    /// Queued frames the sink accepted.
+    pub frames: AtomicU64,
+    /// Bytes the encoder accepted; see `count_bytes`.
+    pub bytes: AtomicU64,
+}
+
+impl WriteStats {
+    /// Wrap `encoder`, the innermost sink, so `bytes` counts what reaches it:
+    /// repeated frames count again, motion-blur sub-frames only once averaged.
+    pub fn count_bytes(self: &Arc<Self>, mut encoder: FrameSink) -> FrameSink {
+        let stats = self.clone();
+        Box::new(move |frame: &[u8]| {
+            encoder(frame)?;
+            stats.bytes.fetch_add(frame.len() as u64, Ordering::Relaxed);
+            Ok(())
+        })
+    }
+}
+
+/// Cloneable handle used to queue frames without holding the session lock.
+pub struct FrameSender {
+    tx: SyncSender<Vec<u8>>,
+    depth: Arc<AtomicUsize>,
+    error: WriteError,
+}
+
+impl FrameWriter {
+    /// Start the writer thread, handing each queued frame to `sink` and
+    /// counting accepted frames in `stats`.
+    pub fn spawn(sink: FrameSink, stats: Arc<WriteStats>) -> Self {
+        let (tx, rx)           = mpsc::sync_channel(FRAME_QUEUE_CAPACITY);
+        let (pool_tx, pool)    = mpsc::channel();
+        let depth              = Arc::new(AtomicUsize::new(0));
+        let error: WriteError  = Arc::default();
+        let handle = {
+            let depth = depth.clone();
+            let stats = stats.clone();
+            let error = error.clone();
+            thread::spawn(move || write_loop(sink, rx, pool_tx, depth, stats, error))
+        };
+        Self { tx, pool, depth, stats, error, handle }
+    }
+
+    /// A buffer for the next frame — recycled if one is available.
+    pub fn buffer(&self) -> Vec<u8> {
+        self.pool.try_recv().unwrap_or_default()
+    }
+
+    pub fn sender(&self) -> FrameSender {
+        FrameSender { tx: self.tx.clone(), depth: self.depth.clone(), error: self.error.clone() }
+    }
+
+    /// Frames currently waiting in the queue.
+    pub fn depth(&self) -> usize {
+        self.depth.load(Ordering::SeqCst)
+    }
+
+    /// Shared with the writer thread, so the totals can still be read after `finish`.
+    pub fn stats(&self) -> Arc<WriteStats> {
+        self.stats.clone()
+    }
+
+    /// The error that stopped the writer thread, if any.
+    pub fn error(&self) -> Option<EncodeError> {
+        self.error.lock().ok().and_then(|e| e.clone())
+    }
+
+    /// Write out every queued frame, then drop the sink.
+    pub fn finish(self) -> Result<(), EncodeError> {
+        let Self { tx, error, handle, .. } = self;
+        drop(tx);
+        handle.join().map_err(|_| EncodeError::Io { message: "Frame writer thread panicked".into() })?;
+        match error.lock().ok().and_then(|e| e.clone()) {
+            Some(e) => Err(e),
+            None    => Ok(()),
+        }
+    }
+}
+
+impl FrameSender {
+    /// Frames currently waiting in the queue.
+    pub fn depth(&self) -> usize {
+        self.depth.load(Ordering::SeqCst)
+    }
+
+    /// Queue a frame, blocking while the queue is full. Returns the queue depth
+    /// including this frame.
+    pub fn send(&self, frame: Vec<u8>) -> Result<usize, EncodeError> {
+        let depth = self.depth.fetch_add(1, Ordering::SeqCst) + 1;
+        if self.tx.send(frame).is_err() {
+            self.depth.fetch_sub(1, Ordering::SeqCst);
+            let error = self.error.lock().ok().and_then(|e| e.clone());
+            return Err(error.unwrap_or_else(|| EncodeError::PipeBroken {
+                message: "Frame writer has stopped".into(),
+                log_tail: String::new(),
+            }));
+        }
+        Ok(depth)
+    }
+}
+
+fn write_loop(
+    mut sink: FrameSink,
+    rx: Receiver<Vec<u8>>,
+    pool: Sender<Vec<u8>>,
+    depth: Arc<AtomicUsize>,
+    stats: Arc<WriteStats>,
+    error: WriteError,
+) {
+    // Returning drops `rx` (failing further sends) and the sink
+    for frame in rx {
+        let result = sink(&frame);
+        depth.fetch_sub(1, Ordering::SeqCst);
+        if let Err(e) = result {
+            if let Ok(mut slot) = error.lock() {
+                *slot = Some(e);
+            }
+            return;
+        }
+        stats.frames.fetch_add(1, Ordering::Relaxed);
+        let _ = pool.send(frame);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_count_only_what_the_sink_accepted() {
+        let stats  = Arc::new(WriteStats::default());
+        let sink   = stats.count_bytes(Box::new(|frame: &[u8]| match frame[0] {
+            0 => Ok(()),
+            _ => Err(EncodeError::Io { message: "disk full".into() }),
+        }));
+        let writer = FrameWriter::spawn(sink, stats.clone());
+        let sender = writer.sender();
+        for frame in [[0], [0], [1]] {
+            let _ = sender.send(frame.to_vec());
+        }
+        assert!(writer.finish().is_err());
+        assert_eq!(stats.frames.load(Ordering::Relaxed), 2);
+        assert_eq!(stats.bytes.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn repeated_frames_count_their_bytes() {
+        let stats  = Arc::new(WriteStats::default());
+        let sink   = crate::ordering::repeat_previous(stats.count_bytes(Box::new(|_: &[u8]| Ok(()))));
+        let writer = FrameWriter::spawn(sink, stats.clone());
+        let sender = writer.sender();
+        for frame in [vec![0; 8], Vec::new(), Vec::new()] {
+            sender.send(frame).unwrap();
+        }
+        drop(sender);
+        writer.finish().unwrap();
+        assert_eq!(stats.frames.load(Ordering::Relaxed), 3);
+        assert_eq!(stats.bytes.load(Ordering::Relaxed), 24);
+    }
+}
@@ -12,10 +12,11 @@ import { FunctionBuilder } from './components/FunctionBuilder';
 import { useFunctionBuilder } from './components/FunctionBuilder/useFunctionBuilder';
 import type { Page } from './components/TopNav';
 import { NodeSearchPalette } from './components/NodeGraph/NodeSearchPalette';
-import { useNodeGraphStore } from './store/useNodeGraphStore';
+import { useNodeGraphStore, readSavedGraph } from './store/useNodeGraphStore';
 import { audioEngine } from './lib/audioEngine';
 import { useBreakpoint, isMobile, isTablet, isDesktop } from './hooks/useBreakpoint';
 import { useShortcuts } from './hooks/useShortcuts';
+import { startRenderQueue } from './utils/renderQueue';
 
 // ── Responsive sizing helpers ─────────────────────────────────────────────────
 function getDefaultPreviewWidth(bp: ReturnType<typeof useBreakpoint>) {
@@ -299,6 +300,28 @@ function App() {
     setPreviewWidth(getDefaultPreviewWidth(bp));
   }, [bp]);
 
+  // ── Render queue worker ─────────────────────────────────────────────────────
+  // Runs queued jobs (src-tauri/src/queue.rs) for as long as the app is open.
+  // Each job names a saved graph, which is compiled and rendered off-screen
+  // at the job's size; the graph being edited is never touched.
+  useEffect(() => {
+    if (!('__TAURI_INTERNALS__' in window)) return;
+    return startRenderQueue({
+      prepare: async (job) => {
+        const handle = offlineRenderRef.current;
+        if (!handle) throw new Error('Offline render not available');
+        const nodes = readSavedGraph(job.graph);
+        if (!nodes) throw new Error(`No saved graph named "${job.graph}"`);
+        const render = handle.renderGraph(nodes, job.width, job.height);
+        return {
+          renderFrame: render.renderAtTime,
+          readPixels: render.readPixels,
+          dispose: render.dispose,
+        };
+      },
+    });
+  }, []);
+
   // ── Keyboard shortcuts ──────────────────────────────────────────────────────
   const addRandomNode = useCallback((type: string) => {
     addNode(type, { x: 200 + Math.random() * 160, y: 120 + Math.random() * 200 });
@@ -75,14 +75,27 @@ const CODEC_LABELS: Record<FfmpegCodec, string> = {
   h264:   'H.264',
   prores: 'ProRes 422 HQ',
   ffv1:   'FFV1 (lossless)',
+  vp9:    'VP9 (WebM)',
+  av1:    'AV1 (WebM)',
+  qtrle:  'QuickTime Animation',
+  png:    'PNG (MOV)',
+  gif:    'GIF',
 };
 
 const CODEC_DESCRIPTIONS: Record<FfmpegCodec, string> = {
   h264:   'CRF 18 · .mp4 · best compatibility',
   prores: 'Apple ProRes · .mov · editing master',
   ffv1:   'Lossless · .mkv · largest file',
+  vp9:    'CRF 30 · .webm · web playback',
+  av1:    'CRF 32 · .webm · smallest file',
+  qtrle:  'Lossless RLE · .mov · keeps alpha',
+  png:    'Lossless PNG · .mov · keeps alpha',
+  gif:    'Optimised palette · .gif · loops for chat & docs',
 };
 
+// Codecs whose description promises alpha — exported with `preserveAlpha`
+const ALPHA_CODECS: ReadonlySet<FfmpegCodec> = new Set<FfmpegCodec>(['qtrle', 'png']);
+
 // Resolution multipliers relative to the canvas's natural size
 const RESOLUTIONS = [
   { label: '1×  (native)', scale: 1 },
@@ -121,6 +134,7 @@ export function ExportModal({ canvas, offlineRender, onClose }: Props) {
   const [frameCount, setFrameCount]             = useState(0);
   const [errorMsg, setErrorMsg]                 = useState('');
   const [outputPath, setOutputPath]             = useState('');
+  const [warnings, setWarnings]                 = useState<string[]>([]);
 
   const recorderRef  = useRef<CanvasRecorder | null>(null);
   const offscreenRef = useRef<HTMLCanvasElement | null>(null);
@@ -227,6 +241,7 @@ export function ExportModal({ canvas, offlineRender, onClose }: Props) {
     }
 
     setErrorMsg('');
+    setWarnings([]);
     setCaptureProgress(0);
     setElapsed(0);
     setFrameCount(0);
@@ -245,8 +260,11 @@ export function ExportModal({ canvas, offlineRender, onClose }: Props) {
         fps,
         duration,
         codec,
+        preserveAlpha: ALPHA_CODECS.has(codec),
         renderFrame: renderAtTime,
         readPixels: handleReadPixels,
+        shouldAbort: () => abortRef.current,
+        onWarnings: setWarnings,
         onProgress: (fraction, frame) => {
           if (abortRef.current) return;
           setCaptureProgress(fraction);
@@ -258,8 +276,8 @@ export function ExportModal({ canvas, offlineRender, onClose }: Props) {
       setOutputPath(path);
       setState('done');
     } catch (err) {
-      const msg = String(err);
-      if (msg === 'Error: cancelled') {
+      const msg = err instanceof Error ? err.message : String(err);
+      if (msg === 'cancelled') {
         setState('idle');
       } else {
         setErrorMsg(msg);
@@ -384,7 +402,7 @@ export function ExportModal({ canvas, offlineRender, onClose }: Props) {
                 <div>
                   <div style={LABEL}>Codec</div>
                   <div style={{ display: 'flex', flexDirection: 'column', gap: '4px' }}>
-                    {(['h264', 'prores', 'ffv1'] as FfmpegCodec[]).map(c => (
+                    {(['h264', 'prores', 'ffv1', 'vp9', 'av1', 'qtrle', 'png', 'gif'] as FfmpegCodec[]).map(c => (
                       <button
                         key={c}
                         onClick={() => setCodec(c)}
@@ -573,6 +591,13 @@ export function ExportModal({ canvas, offlineRender, onClose }: Props) {
           </div>
         )}
 
+        {/* Preflight warnings (tight disk space, overwrite) */}
+        {warnings.length > 0 && (isEncoding || isDone || isError) && (
+          <div style={{ color: '#f9e2af', fontSize: '11px', background: '#2a2516', padding: '8px', borderRadius: '6px', display: 'flex', flexDirection: 'column', gap: '4px' }}>
+            {warnings.map(w => <span key={w}>⚠ {w}</span>)}
+          </div>
+        )}
+
         {/* Error */}
         {(errorMsg || isError) && (
           <div style={{ color: '#f38ba8', fontSize: '11px', background: '#2a1a1a', padding: '8px', borderRadius: '6px' }}>
@@ -584,7 +609,7 @@ export function ExportModal({ canvas, offlineRender, onClose }: Props) {
         <div style={{ display: 'flex', gap: '8px', justifyContent: 'flex-end', marginTop: '4px' }}>
           {(isDone || isError) && (
             <button
-              onClick={() => { setState('idle'); setCaptureProgress(0); setElapsed(0); setFrameCount(0); setErrorMsg(''); setOutputPath(''); }}
+              onClick={() => { setState('idle'); setCaptureProgress(0); setElapsed(0); setFrameCount(0); setErrorMsg(''); setOutputPath(''); setWarnings([]); }}
               style={{ ...BTN_BASE, background: '#313244', color: '#cdd6f4' }}
             >Record Again</button>
           )}
@@ -1,6 +1,8 @@
 import { useRef, useEffect } from 'react';
 import * as THREE from 'three';
 import { useNodeGraphStore } from '../store/useNodeGraphStore';
+import { compileGraph } from '../compiler/graphCompiler';
+import type { GraphNode } from '../types/nodeGraph';
 import { drawScopeCanvas, vectorValueRegistry, floatValueRegistry } from '../lib/scopeRegistry';
 import { audioEngine } from '../lib/audioEngine';
 import { audioSpectrumRegistry, drawSpectrumCanvas } from '../lib/audioSpectrumRegistry';
@@ -75,6 +77,20 @@ export interface OfflineRenderHandle {
   /** Pixel dimensions of the export render target */
   width: number;
   height: number;
+  /**
+   * Compile `nodes` on their own and render them into a separate
+   * `width`×`height` target, leaving the editor's graph and export RT alone.
+   * Throws if the graph doesn't compile or needs live inputs (images, audio,
+   * video, feedback or particles), which only the editor's graph has.
+   */
+  renderGraph: (nodes: GraphNode[], width: number, height: number) => OfflineGraphRender;
+}
+
+/** A graph rendered by `OfflineRenderHandle.renderGraph`; dispose when done */
+export interface OfflineGraphRender {
+  renderAtTime: (time: number) => void;
+  readPixels: (out: Uint8Array, width: number, height: number) => void;
+  dispose: () => void;
 }
 
 // Font texture: 16×16 grid of ASCII chars (codes 0-255), 64×64 px per cell.
@@ -332,33 +348,81 @@ export default function ShaderCanvas({ onCanvasReady, onRegisterOfflineRender, o
         }
       };
 
+      // Render `target` into the float `rt`, then blit with dithering into the 8-bit `readbackRT`
+      const renderToReadback = (
+        target: THREE.Scene, rt: THREE.WebGLRenderTarget, readbackRT: THREE.WebGLRenderTarget, time: number,
+      ) => {
+        renderer.setRenderTarget(rt);
+        renderer.render(target, camera);
+        blitMat.uniforms.tInput.value = rt.texture;
+        blitMat.uniforms.u_seed.value = time * 100.0;
+        renderer.setRenderTarget(readbackRT);
+        renderer.render(blitScene, camera);
+        renderer.setRenderTarget(null);
+      };
+
+      const readFlipped = (readbackRT: THREE.WebGLRenderTarget, out: Uint8Array, width: number, height: number) => {
+        renderer.readRenderTargetPixels(readbackRT, 0, 0, width, height, out);
+        // Flip Y: Three.js RenderTarget is bottom-up; FFmpeg rawvideo expects top-down
+        const rowBytes = width * 4;
+        const tmp = new Uint8Array(rowBytes);
+        for (let y = 0; y < Math.floor(height / 2); y++) {
+          const top = y * rowBytes;
+          const bot = (height - 1 - y) * rowBytes;
+          tmp.set(out.subarray(top, top + rowBytes));
+          out.copyWithin(top, bot, bot + rowBytes);
+          out.set(tmp, bot);
+        }
+      };
+
       const handle: OfflineRenderHandle = {
         get width()  { ensureRT(); return exportW; },
         get height() { ensureRT(); return exportH; },
         renderAtTime: (time: number) => {
           material.uniforms.u_time.value = time;
-          renderer.setRenderTarget(exportRT);
-          renderer.render(scene, camera);
-          // Blit with dithering into 8-bit readback RT
-          blitMat.uniforms.tInput.value = exportRT!.texture;
-          blitMat.uniforms.u_seed.value = time * 100.0;
-          renderer.setRenderTarget(exportReadbackRT);
-          renderer.render(blitScene, camera);
-          renderer.setRenderTarget(null);
+          renderToReadback(scene, exportRT!, exportReadbackRT!, time);
         },
         readPixels: (out: Uint8Array, width: number, height: number) => {
           if (!exportReadbackRT) return;
-          renderer.readRenderTargetPixels(exportReadbackRT, 0, 0, width, height, out);
-          // Flip Y: Three.js RenderTarget is bottom-up; FFmpeg rawvideo expects top-down
-          const rowBytes = width * 4;
-          const tmp = new Uint8Array(rowBytes);
-          for (let y = 0; y < Math.floor(height / 2); y++) {
-            const top = y * rowBytes;
-            const bot = (height - 1 - y) * rowBytes;
-            tmp.set(out.subarray(top, top + rowBytes));
-            out.copyWithin(top, bot, bot + rowBytes);
-            out.set(tmp, bot);
+          readFlipped(exportReadbackRT, out, width, height);
+        },
+        renderGraph: (nodes: GraphNode[], width: number, height: number) => {
+          const result = compileGraph({ nodes });
+          if (!result.success) {
+            throw new Error(`Graph doesn't compile: ${(result.errors ?? []).join('; ')}`);
+          }
+          const liveInputs = { ...result.textureUniforms, ...result.audioUniforms, ...result.videoUniforms };
+          if (Object.keys(liveInputs).length > 0 || result.isStateful || result.particleSystems?.length) {
+            throw new Error("Graphs with image, audio, video, feedback or particle nodes can't be rendered off-screen");
           }
+          const uniforms: Record<string, { value: unknown }> = {
+            u_time:        { value: 0 },
+            u_resolution:  { value: new THREE.Vector2(width, height) },
+            u_mouse:       { value: new THREE.Vector2(0, 0) },
+            u_fontTexture: { value: FONT_TEXTURE },
+          };
+          for (const [name, value] of Object.entries(result.paramUniforms)) uniforms[name] = { value };
+          const graphMaterial = new THREE.ShaderMaterial({
+            vertexShader: result.vertexShader || FALLBACK_VERTEX,
+            fragmentShader: result.fragmentShader || FALLBACK_FRAGMENT,
+            uniforms,
+          });
+          const graphScene = new THREE.Scene();
+          graphScene.add(new THREE.Mesh(geometry, graphMaterial));
+          const rt = new THREE.WebGLRenderTarget(width, height, {
+            type: RT_TYPE, format: THREE.RGBAFormat, depthBuffer: false,
+          });
+          const readbackRT = new THREE.WebGLRenderTarget(width, height, {
+            type: THREE.UnsignedByteType, format: THREE.RGBAFormat, depthBuffer: false,
+          });
+          return {
+            renderAtTime: (time: number) => {
+              graphMaterial.uniforms.u_time.value = time;
+              renderToReadback(graphScene, rt, readbackRT, time);
+            },
+            readPixels: (out: Uint8Array, w: number, h: number) => readFlipped(readbackRT, out, w, h),
+            dispose: () => { graphMaterial.dispose(); rt.dispose(); readbackRT.dispose(); },
+          };
         },
       };
 
@@ -78,6 +78,29 @@ function upgradeExprNodes(nodes: GraphNode[]): GraphNode[] {
   });
 }
 
+/** Read the graph saved as `name`, sanitised and migrated the way
+ *  `loadSavedGraph` loads it, without touching the editor. Returns null if
+ *  there's no such graph or it can't be parsed. */
+export function readSavedGraph(name: string): GraphNode[] | null {
+  const raw = localStorage.getItem(`shader-studio:${name}`);
+  if (!raw) return null;
+  try {
+    const { nodes: rawNodes } = JSON.parse(raw) as { nodes: GraphNode[] };
+    if (!Array.isArray(rawNodes)) return null;
+    // Strip in-memory audio state — audio buffers are not persisted, so
+    // _isPlaying / _hasFile would crash the audio engine on load.
+    const sanitized = rawNodes.map(n => {
+      if (n.type === 'audioInput') {
+        return { ...n, params: { ...n.params, _isPlaying: false, _hasFile: false, _fileName: '' } };
+      }
+      return n;
+    });
+    return upgradeExprNodes(sanitized).map(n => migrateNodeParams(n, getNodeDefinition));
+  } catch {
+    return null;
+  }
+}
+
 /** Convert a label to a filesystem-safe slug. Used by the generic graph-save
  *  feature below; each PresetManager instance has its own copy for presets. */
 function labelToSlug(label: string): string {
@@ -3675,27 +3698,13 @@ export const useNodeGraphStore = create<NodeGraphState>((set, get) => ({
 
   loadSavedGraph: (name) => {
     undoManager.clear();
-    const raw = localStorage.getItem(`shader-studio:${name}`);
-    if (!raw) return;
-    try {
-      const { nodes: rawNodes } = JSON.parse(raw) as { nodes: GraphNode[] };
-      if (Array.isArray(rawNodes)) {
-        // Strip in-memory audio state — audio buffers are not persisted, so
-        // _isPlaying / _hasFile would crash the audio engine on load.
-        const sanitized = rawNodes.map(n => {
-          if (n.type === 'audioInput') {
-            return { ...n, params: { ...n.params, _isPlaying: false, _hasFile: false, _fileName: '' } };
-          }
-          return n;
-        });
-        const nodes = upgradeExprNodes(sanitized).map(n => migrateNodeParams(n, getNodeDefinition));
-        idGenerator.syncFromGraph(nodes);
-        // Reset group navigation so a saved graph that was captured inside a
-        // subgraph doesn't leave the editor stranded in a non-existent group.
-        set({ nodes, previewNodeId: null, activeGroupId: null, activeGroupPath: [] });
-        get().compile();
-      }
-    } catch {}
+    const nodes = readSavedGraph(name);
+    if (!nodes) return;
+    idGenerator.syncFromGraph(nodes);
+    // Reset group navigation so a saved graph that was captured inside a
+    // subgraph doesn't leave the editor stranded in a non-existent group.
+    set({ nodes, previewNodeId: null, activeGroupId: null, activeGroupPath: [] });
+    get().compile();
   },
 
   deleteSavedGraph: (name) => {
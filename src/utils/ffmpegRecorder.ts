@@ -11,11 +11,59 @@
  *        - Set u_time uniform to deterministic value (frame / fps)
  *        - renderer.render(scene, camera)  [via callback]
  *        - gl.readPixels → RGBA Uint8Array
- *        - send_frame_rgba (IPC — writes raw bytes to FFmpeg stdin)
+ *        - send_frame_rgba (raw-body IPC — writes bytes to FFmpeg stdin)
  *   4. stop_ffmpeg_encode  (closes stdin, waits for FFmpeg to finish)
  */
 
-export type FfmpegCodec = 'h264' | 'prores' | 'ffv1';
+export type FfmpegCodec =
+  'h264' | 'prores' | 'ffv1' | 'vp9' | 'av1' | 'qtrle' | 'png' | 'gif';
+
+/** Audio muxed into the export (see `AudioTrack` in src-tauri/src/audio.rs). */
+export interface FfmpegAudioTrack {
+  /** Path to the audio file */
+  path: string;
+  /** Seconds into the audio file where the export starts */
+  offset?: number;
+  /** Seconds of audio to use — defaults to the video length */
+  duration?: number;
+  /** Fade-in length in seconds */
+  fadeIn?: number;
+  /** Fade-out length in seconds, ending at `duration` or the video length */
+  fadeOut?: number;
+  /** Gain in dB */
+  gainDb?: number;
+}
+
+/**
+ * Encoder overrides layered over the codec defaults
+ * (see `EncoderSettings` in src-tauri/src/codec.rs).
+ */
+export interface FfmpegEncoderSettings {
+  crf?: number;
+  /** Target bitrate, e.g. '8M' */
+  bitrate?: string;
+  preset?: string;
+  profile?: string;
+  pixFmt?: string;
+  keyframeInterval?: number;
+  tune?: string;
+  extraArgs?: string[];
+}
+
+export type FfmpegFrameFormat = 'rgba8' | 'rgba16' | 'rgba32f';
+
+const BYTES_PER_PIXEL: Record<FfmpegFrameFormat, number> = {
+  rgba8:   4,
+  rgba16:  8,
+  rgba32f: 16,
+};
+
+/** FFmpeg filter step, validated in Rust (see `FrameFilter` in src-tauri/src/filters.rs) */
+export type FfmpegFrameFilter =
+  | { type: 'vFlip' }
+  | { type: 'scale'; width: number; height: number; kernel?: 'bilinear' | 'bicubic' | 'lanczos' | 'area' | 'gauss' }
+  | { type: 'pad'; aspect: number; color?: string }
+  | { type: 'crop'; x: number; y: number; width: number; height: number };
 
 export interface FfmpegEncodeOptions {
   /** Width in pixels */
@@ -28,6 +76,34 @@ export interface FfmpegEncodeOptions {
   duration: number;
   /** Codec to use */
   codec: FfmpegCodec;
+  /** Overrides for the codec's default encoder args */
+  encoder?: FfmpegEncoderSettings;
+  /** Name of a saved encoder preset — replaces `codec` and `encoder` */
+  preset?: string;
+  /** Keep the alpha channel (prores, ffv1, vp9, qtrle and png only) */
+  preserveAlpha?: boolean;
+  /** Filters applied to each frame in FFmpeg before encoding */
+  filters?: FfmpegFrameFilter[];
+  /**
+   * Temporal supersampling: render `samples` sub-frames per output frame over
+   * `shutterAngle` degrees of the frame interval (180 = film-like blur).
+   */
+  motionBlur?: { samples: number; shutterAngle: number };
+  /**
+   * Frame layout handed to `readPixels`: 'rgba8' (default), 'rgba16' or
+   * 'rgba32f' (scene-linear floats). Deep formats use the same Uint8Array,
+   * viewed as Uint16Array/Float32Array by the caller.
+   */
+  inputFormat?: FfmpegFrameFormat;
+  /**
+   * Write BT.2020 HDR with PQ or HLG transfer. 'rgba32f' frames are converted
+   * from scene-linear; 'rgba8'/'rgba16' frames must already be PQ/HLG-encoded.
+   * Needs a pixel format deeper than 8 bits (e.g. ProRes, or `pixFmt` set to
+   * 'yuv420p10le' for h264/vp9/av1).
+   */
+  hdr?: 'pq' | 'hlg';
+  /** Optional audio track to mux into the output */
+  audio?: FfmpegAudioTrack;
   /**
    * Called once per frame.
    * Implementation should:
@@ -43,11 +119,154 @@ export interface FfmpegEncodeOptions {
   readPixels: (out: Uint8Array, width: number, height: number) => void;
   /** Progress callback — called with fractionComplete (0..1) */
   onProgress?: (fraction: number, frame: number, total: number) => void;
+  /**
+   * Polled before each frame. Returning true aborts the encode: FFmpeg is
+   * killed, the partial file is removed and the promise rejects with 'cancelled'.
+   */
+  shouldAbort?: () => boolean;
+  /** Called with preflight warnings (tight disk space, overwrite) before encoding starts */
+  onWarnings?: (warnings: string[]) => void;
+  /** What to do about a frame that never reaches Rust — defaults to 'repeat' */
+  gapPolicy?: 'repeat' | 'fail';
+  /** Called with the session's frame map once the encode has finished */
+  onFrameMap?: (map: FfmpegFrameMap) => void;
+  /**
+   * Hash of the graph being rendered. When set, the frames are also stored in
+   * the frame cache so `reencodeFromCache` can encode them again later.
+   */
+  cacheGraphHash?: string;
+}
+
+/** Identifies a cached render (see `CacheKey` in src-tauri/src/cache.rs) */
+export interface FfmpegCacheKey {
+  graphHash: string;
+  width: number;
+  height: number;
+  fps: number;
+  /** Time range in seconds */
+  start: number;
+  end: number;
 }
 
+/** Returned by `stop_ffmpeg_encode` (see `FrameMap` in src-tauri/src/ordering.rs) */
+export interface FfmpegFrameMap {
+  /** Frames written, including repeats */
+  frames: number;
+  /** Indices that never arrived and were filled with the previous frame */
+  repeated: number[];
+  /** Indices received more than once */
+  duplicates: number[];
+  /** Indices that arrived after their slot had been filled */
+  late: number[];
+  /** Why the render wasn't stored in the frame cache, if caching was requested */
+  cacheWarning: string | null;
+}
+
+/** Result of `preflight_ffmpeg_encode` (see src-tauri/src/preflight.rs) */
+export interface FfmpegPreflight {
+  estimatedBytes: number | null;
+  freeBytes: number | null;
+  warnings: string[];
+}
+
+/** Container file extension for each codec */
+const CODEC_EXTENSIONS: Record<FfmpegCodec, string> = {
+  h264:   'mp4',
+  prores: 'mov',
+  ffv1:   'mkv',
+  vp9:    'webm',
+  av1:    'webm',
+  qtrle:  'mov',
+  png:    'mov',
+  gif:    'gif',
+};
+
+/** Structured error returned by the Rust commands (see `EncodeError` in src-tauri/src/error.rs) */
+export type EncodeError =
+  | { kind: 'ffmpegNotFound'; path: string | null }
+  | { kind: 'downloadFailed'; message: string }
+  | { kind: 'sessionBusy'; outputPath: string }
+  | { kind: 'noSession'; sessionId: number }
+  | { kind: 'frameSizeMismatch'; got: number; expected: number }
+  | { kind: 'missingFrame'; index: number }
+  | { kind: 'pipeBroken'; message: string; logTail: string }
+  | { kind: 'nonZeroExit'; code: number | null; logTail: string }
+  | { kind: 'outputNotWritable'; path: string; message: string }
+  | { kind: 'insufficientSpace'; path: string; requiredBytes: number; freeBytes: number; keptPath: string | null }
+  | { kind: 'lockPoisoned' }
+  | { kind: 'invalid'; message: string }
+  | { kind: 'io'; message: string };
+
+const mib = (bytes: number) => Math.round(bytes / (1024 * 1024));
+
+export function describeEncodeError(e: EncodeError): string {
+  switch (e.kind) {
+    case 'ffmpegNotFound':    return e.path ? `Configured FFmpeg not found: ${e.path}` : 'FFmpeg not found';
+    case 'downloadFailed':    return `FFmpeg download failed: ${e.message}`;
+    case 'sessionBusy':       return `A session is already writing to ${e.outputPath}`;
+    case 'noSession':         return `No active session ${e.sessionId}`;
+    case 'frameSizeMismatch': return `Frame size mismatch: got ${e.got} bytes, expected ${e.expected}`;
+    case 'missingFrame':      return `Frame ${e.index} never arrived`;
+    case 'pipeBroken':        return e.logTail ? `${e.message}\n${e.logTail}` : e.message;
+    case 'nonZeroExit':       return `FFmpeg exited with code ${e.code ?? 'none'}${e.logTail ? `\n${e.logTail}` : ''}`;
+    case 'outputNotWritable': return `Can't write ${e.path}: ${e.message}`;
+    case 'insufficientSpace': return `Not enough space for ${e.path}: needs about ${mib(e.requiredBytes)} MiB, ${mib(e.freeBytes)} MiB free`
+      + (e.keptPath ? `; the truncated output was kept at ${e.keptPath}` : '');
+    case 'lockPoisoned':      return 'Session state lock poisoned';
+    case 'invalid':
+    case 'io':                return e.message;
+  }
+}
+
+/** Thrown by `runFfmpegEncode` when a Rust command fails; `detail` holds the structured error. */
+export class FfmpegEncodeError extends Error {
+  constructor(public readonly detail: EncodeError) {
+    super(describeEncodeError(detail));
+    this.name = 'FfmpegEncodeError';
+  }
+}
+
+const isEncodeError = (e: unknown): e is EncodeError =>
+  typeof e === 'object' && e !== null && 'kind' in e;
+
 const isTauri = () =>
   typeof window !== 'undefined' && '__TAURI_INTERNALS__' in window;
 
+/**
+ * Run `start`, which returns a job id, and hand `onEvent` every `event` payload
+ * for that id until it returns true. Listens first: a short job can finish
+ * before invoke returns, so earlier payloads are held until the id is known.
+ */
+async function followJob<T>(
+  event: string,
+  idOf: (payload: T) => number,
+  start: () => Promise<number>,
+  onEvent: (payload: T) => boolean,
+): Promise<number> {
+  const { listen } = await import('@tauri-apps/api/event');
+
+  const early: T[] = [];
+  let id: number | null = null;
+  let done = false;
+  const handle = (payload: T) => {
+    if (done || idOf(payload) !== id) return;
+    done = onEvent(payload);
+    if (done) unlisten();
+  };
+  const unlisten = await listen<T>(event, e => {
+    if (id === null) early.push(e.payload); else handle(e.payload);
+  });
+
+  try {
+    id = await start();
+  } catch (err) {
+    unlisten();
+    throw isEncodeError(err) ? new FfmpegEncodeError(err) : err;
+  }
+  early.splice(0).forEach(handle);
+  return id;
+}
+
 /**
  * Start an FFmpeg offline encode.
  * Returns a promise that resolves to the output file path, or throws on error.
@@ -57,16 +276,22 @@ export async function runFfmpegEncode(opts: FfmpegEncodeOptions): Promise<string
     throw new Error('FFmpeg encoding is only available in the desktop app.');
   }
 
-  const { invoke } = await import('@tauri-apps/api/core');
+  const { invoke: rawInvoke } = await import('@tauri-apps/api/core');
+  // Re-throw structured command errors as FfmpegEncodeError so callers get a message
+  const invoke: typeof rawInvoke = async (cmd, args, options) => {
+    try {
+      return await rawInvoke(cmd, args, options);
+    } catch (err) {
+      throw isEncodeError(err) ? new FfmpegEncodeError(err) : err;
+    }
+  };
   const { save }   = await import('@tauri-apps/plugin-dialog');
 
-  const ext = opts.codec === 'prores' ? 'mov'
-            : opts.codec === 'ffv1'   ? 'mkv'
-            : 'mp4';
+  const ext = CODEC_EXTENSIONS[opts.codec];
 
   const outputPath = await save({
     defaultPath: `shader-export-${Date.now()}.${ext}`,
-    filters: [{ name: 'Video', extensions: [ext] }],
+    filters: [{ name: opts.codec === 'gif' ? 'GIF' : 'Video', extensions: [ext] }],
   });
 
   if (!outputPath) {
@@ -75,32 +300,73 @@ export async function runFfmpegEncode(opts: FfmpegEncodeOptions): Promise<string
 
   const totalFrames = Math.ceil(opts.duration * opts.fps);
 
-  // Start the Rust/FFmpeg session
-  await invoke('start_ffmpeg_encode', {
+  const encodeOptions = {
     outputPath,
-    width:  opts.width,
-    height: opts.height,
-    fps:    opts.fps,
-    codec:  opts.codec,
-  });
+    width:   opts.width,
+    height:  opts.height,
+    fps:     opts.fps,
+    encoder: { ...opts.encoder, codec: opts.codec },
+    preset:  opts.preset ?? null,
+    audio:   opts.audio ?? null,
+    filters: opts.filters ?? [],
+    motionBlur: opts.motionBlur ?? null,
+    inputFormat: opts.inputFormat ?? 'rgba8',
+    hdr: opts.hdr ?? null,
+    expectedFrames: totalFrames,
+    preserveAlpha: opts.preserveAlpha ?? false,
+    gapPolicy: opts.gapPolicy ?? 'repeat',
+    cache: opts.cacheGraphHash === undefined ? null : {
+      graphHash: opts.cacheGraphHash,
+      width: opts.width,
+      height: opts.height,
+      fps: opts.fps,
+      start: 0,
+      end: opts.duration,
+    },
+  };
+
+  // Fails early if the directory isn't writable or the output clearly won't fit
+  const preflight = await invoke<FfmpegPreflight>('preflight_ffmpeg_encode', { options: encodeOptions });
+  if (preflight.warnings.length > 0) opts.onWarnings?.(preflight.warnings);
+
+  // Start the Rust/FFmpeg session — the returned id addresses it from here on
+  const sessionId = await invoke<number>('start_ffmpeg_encode', { options: encodeOptions });
+
+  const samples = opts.motionBlur?.samples ?? 1;
+  const shutter = (opts.motionBlur?.shutterAngle ?? 0) / 360;
 
   // Reusable pixel buffer — allocated once, reused every frame
-  const pixelBuf = new Uint8Array(opts.width * opts.height * 4);
+  const pixelBuf = new Uint8Array(
+    opts.width * opts.height * BYTES_PER_PIXEL[opts.inputFormat ?? 'rgba8'],
+  );
 
   try {
     for (let i = 0; i < totalFrames; i++) {
-      const time = i / opts.fps;
+      if (opts.shouldAbort?.()) {
+        throw new Error('cancelled');
+      }
+
+      // With motion blur, render `samples` sub-frames across the open shutter;
+      // Rust averages them into one output frame.
+      for (let s = 0; s < samples; s++) {
+        const time = (i + shutter * (s / samples)) / opts.fps;
 
-      // Render deterministic frame
-      opts.renderFrame(time);
+        // Render deterministic frame
+        opts.renderFrame(time);
 
-      // Read pixels into the reusable buffer
-      opts.readPixels(pixelBuf, opts.width, opts.height);
+        // Read pixels into the reusable buffer
+        opts.readPixels(pixelBuf, opts.width, opts.height);
 
-      // Send raw RGBA bytes to Rust → FFmpeg stdin.
-      // Pass the Uint8Array directly — Tauri v2 transfers typed arrays as binary
-      // (no Array.from conversion needed, which was an O(n) copy per frame).
-      await invoke('send_frame_rgba', { data: pixelBuf });
+        // Send raw RGBA bytes to Rust → FFmpeg stdin.
+        // The buffer is the raw IPC body (not a JSON argument), so it is transferred
+        // as binary; the session id and frame index ride along in headers.
+        await invoke('send_frame_rgba', pixelBuf, {
+          headers: {
+            'x-session-id': String(sessionId),
+            'x-frame-index': String(i * samples + s),
+          },
+        });
+      }
 
       opts.onProgress?.(i / totalFrames, i, totalFrames);
 
@@ -111,14 +377,164 @@ export async function runFfmpegEncode(opts: FfmpegEncodeOptions): Promise<string
       }
     }
   } catch (err) {
-    // Try to clean up the Rust session before re-throwing
-    try { await invoke('stop_ffmpeg_encode'); } catch { /* ignore */ }
+    if (err instanceof FfmpegEncodeError && err.detail.kind === 'insufficientSpace') {
+      // Out of disk: let FFmpeg finalise what was written. It's kept without
+      // replacing an existing file, and the error thrown by stopping says where.
+      await invoke('stop_ffmpeg_encode', { sessionId });
+    } else {
+      // Kill FFmpeg and remove the partial file before re-throwing
+      try { await invoke('abort_ffmpeg_encode', { sessionId }); } catch { /* ignore */ }
+    }
     throw err;
   }
 
   // Finalise — this blocks until FFmpeg has finished writing
-  await invoke('stop_ffmpeg_encode');
+  const frameMap = await invoke<FfmpegFrameMap>('stop_ffmpeg_encode', { sessionId });
+  opts.onFrameMap?.(frameMap);
 
   opts.onProgress?.(1, totalFrames, totalFrames);
   return outputPath;
 }
+
+/**
+ * Encode a cached render to `outputPath` with different codec settings, without
+ * rendering it again. Resolves with the frame map once FFmpeg has finished.
+ */
+export async function reencodeFromCache(
+  key: FfmpegCacheKey,
+  outputPath: string,
+  codec: FfmpegCodec,
+  encoder?: FfmpegEncoderSettings,
+): Promise<FfmpegFrameMap> {
+  if (!isTauri()) {
+    throw new Error('FFmpeg encoding is only available in the desktop app.');
+  }
+  const { invoke } = await import('@tauri-apps/api/core');
+
+  type Finished = { sessionId: number; frameMap: FfmpegFrameMap | null; error: EncodeError | null };
+  let settle: (f: Finished) => void = () => {};
+  const finished = new Promise<Finished>(resolve => { settle = resolve; });
+  await followJob<Finished>(
+    'cache-reencode',
+    f => f.sessionId,
+    () => invoke<number>('reencode_from_cache', {
+      key,
+      options: {
+        outputPath,
+        width: key.width,
+        height: key.height,
+        fps: key.fps,
+        encoder: { ...encoder, codec },
+      },
+    }),
+    f => { settle(f); return true; },
+  );
+
+  const result = await finished;
+  if (result.error !== null) throw new FfmpegEncodeError(result.error);
+  if (!result.frameMap) throw new Error('Re-encode failed');
+  return result.frameMap;
+}
+
+// ── Edit jobs ─────────────────────────────────────────────────────────────────
+
+/** Re-encode settings for an edit; omit to copy the streams. */
+export type FfmpegEditEncoder = FfmpegEncoderSettings & { codec: FfmpegCodec };
+
+/** See `TrimOptions` in src-tauri/src/edit.rs */
+export interface FfmpegTrimOptions {
+  input: string;
+  outputPath: string;
+  /** Seconds */
+  start: number;
+  end: number;
+  /** Without it cuts land on keyframes */
+  encoder?: FfmpegEditEncoder;
+  preserveAlpha?: boolean;
+}
+
+/** See `ConcatOptions` in src-tauri/src/edit.rs */
+export interface FfmpegConcatOptions {
+  /** Joined in order; must share resolution and frame rate */
+  inputs: string[];
+  outputPath: string;
+  /** Crossfade length in seconds — needs `encoder` */
+  crossfade?: number;
+  encoder?: FfmpegEditEncoder;
+  preserveAlpha?: boolean;
+}
+
+/** See `TranscodeOptions` in src-tauri/src/edit.rs */
+export interface FfmpegTranscodeOptions {
+  input: string;
+  outputPath: string;
+  encoder: FfmpegEditEncoder;
+  preserveAlpha?: boolean;
+}
+
+/** Payload of the `edit-progress` event */
+export interface FfmpegEditProgress {
+  jobId: number;
+  /** 0–1 */
+  fraction: number;
+  outTimeUs: number;
+  speed: number;
+  done: boolean;
+  cancelled: boolean;
+  error: EncodeError | null;
+}
+
+export interface FfmpegEditJob {
+  jobId: number;
+  /** Resolves with the output path; rejects on error or cancellation */
+  finished: Promise<string>;
+  cancel: () => Promise<void>;
+}
+
+async function startEditJob(
+  command: 'start_trim_job' | 'start_concat_job' | 'start_transcode_job',
+  options: { outputPath: string },
+  onProgress?: (p: FfmpegEditProgress) => void,
+): Promise<FfmpegEditJob> {
+  if (!isTauri()) {
+    throw new Error('FFmpeg editing is only available in the desktop app.');
+  }
+  const { invoke } = await import('@tauri-apps/api/core');
+
+  let resolve: (path: string) => void = () => {};
+  let reject: (err: Error) => void = () => {};
+  const finished = new Promise<string>((res, rej) => { resolve = res; reject = rej; });
+  const jobId = await followJob<FfmpegEditProgress>(
+    'edit-progress',
+    p => p.jobId,
+    () => invoke<number>(command, { options }),
+    p => {
+      onProgress?.(p);
+      if (!p.done && !p.cancelled && p.error === null) return false;
+      if (p.done) resolve(options.outputPath);
+      else reject(p.error ? new FfmpegEncodeError(p.error) : new Error('Edit cancelled'));
+      return true;
+    },
+  );
+
+  return {
+    jobId,
+    finished,
+    cancel: () => invoke<void>('cancel_edit_job', { jobId }),
+  };
+}
+
+/** Cut `start`–`end` out of an existing export. */
+export function trimExport(opts: FfmpegTrimOptions, onProgress?: (p: FfmpegEditProgress) => void) {
+  return startEditJob('start_trim_job', opts, onProgress);
+}
+
+/** Join existing exports, optionally crossfading between them. */
+export function concatExports(opts: FfmpegConcatOptions, onProgress?: (p: FfmpegEditProgress) => void) {
+  return startEditJob('start_concat_job', opts, onProgress);
+}
+
+/** Re-encode an existing export with other codec settings. */
+export function transcodeExport(opts: FfmpegTranscodeOptions, onProgress?: (p: FfmpegEditProgress) => void) {
+  return startEditJob('start_transcode_job', opts, onProgress);
+}
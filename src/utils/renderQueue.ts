@@ -0,0 +1,135 @@
+/**
+ * renderQueue — frontend worker for the Rust render queue (src-tauri/src/queue.rs).
+ *
+ * Rust stores the jobs and runs one encode session at a time; this worker
+ * claims each job, renders its frames through the hooks returned by
+ * `prepare`, and reports back:
+ *   1. claim_render_job     (starts the job's encode session)
+ *   2. send_frame_rgba      (once per frame, indexed)
+ *   3. complete_render_job  (finalises the encode, or aborts it on error)
+ *
+ * Status changes are broadcast as `render-job` events. The app starts one
+ * worker from `App`, rendering each job's saved graph off-screen.
+ */
+
+import {
+  describeEncodeError,
+  type EncodeError,
+  type FfmpegCodec,
+  type FfmpegEncoderSettings,
+} from './ffmpegRecorder';
+
+export type RenderJobStatus = 'queued' | 'running' | 'done' | 'failed' | 'cancelled';
+
+/** What to render (see `RenderJobSpec` in src-tauri/src/queue.rs) */
+export interface RenderJobSpec {
+  /** Name of the saved graph to render, handed back to `prepare` */
+  graph: string;
+  width: number;
+  height: number;
+  fps: number;
+  /** Seconds */
+  duration: number;
+  encoder: FfmpegEncoderSettings & { codec: FfmpegCodec };
+  preserveAlpha?: boolean;
+  outputPath: string;
+}
+
+export interface RenderJob extends RenderJobSpec {
+  id: number;
+  status: RenderJobStatus;
+  error: string | null;
+  sessionId: number | null;
+}
+
+export interface RenderQueueSnapshot {
+  paused: boolean;
+  jobs: RenderJob[];
+}
+
+/** Payload of the `render-job` event */
+export interface RenderJobEvent {
+  jobId: number;
+  status: RenderJobStatus;
+  error: string | null;
+}
+
+/** Frame hooks for one job's graph */
+export interface PreparedJob {
+  renderFrame: (time: number) => void;
+  readPixels: (out: Uint8Array, width: number, height: number) => void;
+  dispose?: () => void;
+}
+
+export interface RenderQueueWorker {
+  /** Load `job.graph` and return hooks that render it at the job's size */
+  prepare: (job: RenderJob) => Promise<PreparedJob>;
+  onProgress?: (job: RenderJob, fraction: number) => void;
+}
+
+/** How long to wait before checking for new jobs when the queue is idle */
+const IDLE_POLL_MS = 2000;
+
+const errorMessage = (err: unknown) =>
+  typeof err === 'object' && err !== null && 'kind' in err
+    ? describeEncodeError(err as EncodeError)
+    : String(err);
+
+/**
+ * Process the queue until the returned stop function is called. Stopping
+ * lets the current job finish; pause the queue in Rust to hold later ones.
+ */
+export function startRenderQueue(worker: RenderQueueWorker): () => void {
+  let stopped = false;
+
+  const run = async () => {
+    const { invoke } = await import('@tauri-apps/api/core');
+
+    while (!stopped) {
+      let job: RenderJob | null;
+      try {
+        job = await invoke<RenderJob | null>('claim_render_job');
+      } catch (err) {
+        console.error('[renderQueue] claim failed:', errorMessage(err));
+        job = null;
+      }
+      if (!job || job.sessionId === null) {
+        await new Promise<void>(r => setTimeout(r, IDLE_POLL_MS));
+        continue;
+      }
+
+      let error: string | null = null;
+      let prepared: PreparedJob | null = null;
+      try {
+        prepared = await worker.prepare(job);
+        const pixels = new Uint8Array(job.width * job.height * 4);
+        const total  = Math.ceil(job.duration * job.fps);
+        for (let i = 0; i < total; i++) {
+          prepared.renderFrame(i / job.fps);
+          prepared.readPixels(pixels, job.width, job.height);
+          await invoke('send_frame_rgba', pixels, {
+            headers: { 'x-session-id': String(job.sessionId), 'x-frame-index': String(i) },
+          });
+          worker.onProgress?.(job, (i + 1) / total);
+          if (i % 5 === 0) {
+            await new Promise<void>(r => setTimeout(r, 0));
+          }
+        }
+      } catch (err) {
+        // Also lands here when the job was cancelled and its session aborted
+        error = errorMessage(err);
+      } finally {
+        prepared?.dispose?.();
+      }
+
+      try {
+        await invoke('complete_render_job', { jobId: job.id, error });
+      } catch (err) {
+        console.error('[renderQueue] complete failed:', errorMessage(err));
+      }
+    }
+  };
+
+  void run();
+  return () => { stopped = true; };
+}